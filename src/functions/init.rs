@@ -0,0 +1,77 @@
+use std::env;
+use std::path::Path;
+use anyhow::{bail, Context};
+use aws_sdk_s3::Client;
+use chrono::{SecondsFormat, Utc};
+use dotenv::dotenv;
+use crate::tools;
+use crate::tools::aws::BucketStatus;
+use crate::tools::ignore_rules::DEFAULT_IGNORE_PATTERNS;
+use crate::utils::project::Project;
+use crate::utils::project_config::{ProjectConfig, ProjectDescriptor};
+use crate::utils::user::User;
+
+pub async fn init_project(project: &Project, create_missing_bucket: bool, client: &Client) -> anyhow::Result<ProjectConfig> {
+    dotenv().ok();
+    let project_dir = Path::new(&project.path);
+    println!("Initializing project '{}' at: {}", project.name, project_dir.display());
+
+    // A config that fails to parse is reported rather than replaced, it holds the user's settings
+    let config = if ProjectConfig::config_path(project_dir).exists() {
+        let config = ProjectConfig::load(project_dir)?;
+        println!("Project already linked to s3://{}/{}", config.bucket, config.remote_prefix);
+        config
+    } else {
+        let bucket_name = env::var("BUCKET_NAME").context("BUCKET_NAME not set")?;
+        ProjectConfig::new(&project.name, &bucket_name)
+    };
+
+    match tools::aws::check_bucket(client, &config.bucket).await? {
+        BucketStatus::Available => println!("Bucket '{}' is available", config.bucket),
+        BucketStatus::AccessDenied => bail!(
+            "Access to bucket '{}' denied, check YANDEX_ACCESS_KEY_ID and YANDEX_SECRET_ACCESS_KEY_ID",
+            config.bucket
+        ),
+        BucketStatus::Missing if create_missing_bucket => {
+            tools::aws::create_bucket(client, &config.bucket).await?;
+            println!("Bucket '{}' created", config.bucket);
+        }
+        BucketStatus::Missing => bail!(
+            "Bucket '{}' does not exist, use 'init --create-bucket' to create it",
+            config.bucket
+        ),
+    }
+
+    let descriptor_key = config.descriptor_key();
+    if tools::aws::object_exists(client, &config.bucket, &descriptor_key).await? {
+        let data = tools::aws::get_bytes(client, &config.bucket, &descriptor_key).await?;
+        let descriptor: ProjectDescriptor = serde_json::from_slice(&data)
+            .with_context(|| format!("Failed to parse remote descriptor '{}'", descriptor_key))?;
+        if descriptor.name != project.name {
+            bail!(
+                "Remote project at '{}' belongs to '{}', not '{}'",
+                descriptor_key, descriptor.name, project.name
+            );
+        }
+        println!(
+            "Linked to existing remote project created by {} at {}",
+            descriptor.created_by, descriptor.created_at
+        );
+    } else {
+        let descriptor = ProjectDescriptor {
+            name: project.name.clone(),
+            engine_association: project.get_engine_association().unwrap_or_default(),
+            created_by: User::get_user_name(),
+            created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            default_excludes: DEFAULT_IGNORE_PATTERNS.iter().map(|p| p.to_string()).collect(),
+        };
+        let data = serde_json::to_vec_pretty(&descriptor)?;
+        tools::aws::put_bytes(client, &config.bucket, &descriptor_key, data).await?;
+        println!("Remote project created at s3://{}/{}", config.bucket, descriptor_key);
+    }
+
+    config.save(project_dir)?;
+    println!("Local config written to {}", ProjectConfig::config_path(project_dir).display());
+
+    Ok(config)
+}
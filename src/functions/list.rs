@@ -0,0 +1,142 @@
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+use anyhow::Context;
+use aws_sdk_s3::Client;
+use crate::functions::pull;
+use crate::tools::chunk_store::Storage;
+use crate::tools::compressing::{ArchiveEntryInfo, PasswordError};
+use crate::tools::ignore_rules::PathSelection;
+use crate::tools::progress::Progress;
+use crate::tools::remote_archive::RemoteArchive;
+use crate::utils::backup_meta::{BackupFile, BackupMeta};
+use crate::utils::project_config::ProjectConfig;
+
+/// GET size for reading an archive index; the 7z end header or the zip central directory
+/// of a project backup usually fits into one or two blocks.
+const INDEX_BLOCK_SIZE: u64 = 256 * 1024;
+/// GET size for archives without an index, which are streamed to the end.
+const STREAM_BLOCK_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    /// Backup to list; the latest one when `None`.
+    pub backup_id: Option<String>,
+    /// Globs the listing is limited to, e.g. `Content/Maps/**`; empty lists everything.
+    pub paths: Vec<String>,
+}
+
+pub struct ListPlan {
+    pub bucket: String,
+    pub meta: BackupMeta,
+    pub paths: Vec<String>,
+    /// Password for a backup whose entry names are encrypted, see [`ListPlan::needs_password`].
+    pub password: Option<String>,
+}
+
+impl ListPlan {
+    /// Whether the entry names can only be read with the archive password.
+    pub fn needs_password(&self) -> bool {
+        self.meta.encrypted && self.meta.format.compressor().encrypts_names()
+    }
+}
+
+pub struct BackupListing {
+    pub object_key: String,
+    pub archive_size: u64,
+    /// Matching entries sorted by path.
+    pub entries: Vec<ArchiveEntryInfo>,
+    /// Bytes of the archive downloaded to read them.
+    pub fetched: u64,
+}
+
+pub async fn plan_list(project_path: &Path, client: &Client, options: &ListOptions) -> anyhow::Result<ListPlan> {
+    let config = ProjectConfig::load(project_path)?;
+    PathSelection::new(&options.paths)?;
+    let meta = pull::find_backup(&config, client, options.backup_id.as_deref()).await?;
+
+    Ok(ListPlan {
+        bucket: config.bucket,
+        meta,
+        paths: options.paths.clone(),
+        password: None,
+    })
+}
+
+/// Reads the entries of a backup straight from the bucket with ranged GETs, fetching only the
+/// archive index when the format has one. A backup in the chunk store is listed from its manifest.
+pub async fn list_backup(plan: ListPlan, client: &Client) -> anyhow::Result<BackupListing> {
+    if plan.needs_password() && plan.password.is_none() {
+        return Err(PasswordError::Required).with_context(|| format!("Failed to list {}", plan.meta.object_key));
+    }
+
+    let (mut entries, fetched) = match plan.meta.storage {
+        Storage::Archive => read_archive_entries(&plan, client).await?,
+        Storage::Chunks => {
+            let mut entries: Vec<_> = plan.meta.files.iter().map(manifest_entry).collect();
+            entries.extend(plan.meta.empty_dirs.iter().map(|path| ArchiveEntryInfo {
+                path: path.clone(),
+                size: 0,
+                modified: None,
+                is_dir: true,
+                is_link: false,
+            }));
+            entries.extend(plan.meta.links.iter().map(|link| ArchiveEntryInfo {
+                path: link.path.clone(),
+                size: 0,
+                modified: None,
+                is_dir: false,
+                is_link: true,
+            }));
+            (entries, 0)
+        }
+    };
+
+    let selection = PathSelection::new(&plan.paths)?;
+    entries.retain(|e| plan.paths.is_empty() || selection.matches(&e.path));
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(BackupListing {
+        object_key: plan.meta.object_key,
+        archive_size: plan.meta.archive_size,
+        entries,
+        fetched,
+    })
+}
+
+/// Entries of the backup's archive and the unchanged files an incremental backup only lists in
+/// its manifest, with the bytes of the archive read.
+async fn read_archive_entries(plan: &ListPlan, client: &Client) -> anyhow::Result<(Vec<ArchiveEntryInfo>, u64)> {
+    let format = plan.meta.format;
+    let (block_size, total) = if format.compressor().has_index() {
+        (INDEX_BLOCK_SIZE, None)
+    } else {
+        println!("{} archives have no index, reading the whole archive...", format);
+        (STREAM_BLOCK_SIZE, Some(plan.meta.archive_size))
+    };
+    let progress = Progress::new("Listing", total);
+    let mut archive = RemoteArchive::new(client, &plan.bucket, &plan.meta, block_size, &progress);
+    let password = plan.password.clone();
+    let (entries, fetched) = tokio::task::spawn_blocking(move || {
+        let entries = format.compressor().list(&mut archive, password.as_deref());
+        (entries, archive.fetched())
+    })
+    .await?;
+    match entries {
+        Ok(_) => progress.finish("Listing complete!"),
+        Err(_) => progress.abandon(),
+    }
+    let mut entries = entries.with_context(|| format!("Failed to list {}", plan.meta.object_key))?;
+    // Unchanged files of an incremental backup are only in the manifest
+    entries.extend(plan.meta.files.iter().filter(|f| f.stored_in.is_some()).map(manifest_entry));
+    Ok((entries, fetched))
+}
+
+fn manifest_entry(file: &BackupFile) -> ArchiveEntryInfo {
+    ArchiveEntryInfo {
+        path: file.path.clone(),
+        size: file.size,
+        modified: file.mtime_ns.map(|ns| UNIX_EPOCH + Duration::from_nanos(ns)),
+        is_dir: false,
+        is_link: false,
+    }
+}
@@ -0,0 +1,31 @@
+use std::path::Path;
+use aws_sdk_s3::Client;
+use crate::functions::pull;
+use crate::tools;
+use crate::utils::backup_meta::BackupMeta;
+use crate::utils::project_config::ProjectConfig;
+
+#[derive(Debug, Default)]
+pub struct LogOptions {
+    /// Most recent snapshots shown; `None` shows the whole history.
+    pub limit: Option<usize>,
+}
+
+/// Snapshots of the project, newest first.
+pub async fn snapshot_log(project_path: &Path, client: &Client, options: &LogOptions) -> anyhow::Result<Vec<BackupMeta>> {
+    let config = ProjectConfig::load(project_path)?;
+    let mut keys: Vec<_> = tools::aws::list_keys(client, &config.bucket, &config.backups_prefix())
+        .await?
+        .into_iter()
+        .filter(|k| k.ends_with(".json"))
+        .collect();
+    // Ids are UTC timestamps, so the keys sort in push order
+    keys.sort_by(|a, b| b.cmp(a));
+    keys.truncate(options.limit.unwrap_or(keys.len()));
+
+    let mut snapshots = Vec::with_capacity(keys.len());
+    for key in keys {
+        snapshots.push(pull::load_meta(&config, client, &key).await?);
+    }
+    Ok(snapshots)
+}
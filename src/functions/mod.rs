@@ -1,2 +1,7 @@
+pub mod init;
 pub mod push;
-pub mod pull;
\ No newline at end of file
+pub mod pull;
+pub mod verify;
+pub mod list;
+pub mod log;
+pub mod status;
\ No newline at end of file
@@ -1,25 +1,329 @@
-use std::{env, fs};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
-use anyhow::Context;
-use dotenv::dotenv;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use anyhow::{bail, Context};
+use aws_sdk_s3::Client;
+use crate::functions::push::TransferSummary;
 use crate::tools;
+use crate::tools::checksum::ChecksumMismatch;
+use crate::tools::chunk_store::{ChunkReader, Storage};
+use crate::tools::compressing::{EntryMetadata, EntryWriter, ExtractOptions, ExtractReport, OverwritePolicy, PasswordError};
+use crate::tools::ignore_rules::PathSelection;
+use crate::tools::progress::Progress;
+use crate::utils::backup_meta::{BackupFile, BackupMeta};
+use crate::utils::local_state::LocalSnapshot;
+use crate::utils::project_config::ProjectConfig;
 
-pub async fn restore_backup(project_name: &str, target_path: &Path) -> anyhow::Result<()> {
-    dotenv().ok();
-    println!("Restoring project '{}' to: {}", project_name, target_path.display());
+#[derive(Debug, Default)]
+pub struct PullOptions {
+    /// Snapshot to restore; the latest one when `None`.
+    pub backup_id: Option<String>,
+    /// Globs of the paths to restore, e.g. `Content/Maps/**`; empty restores the whole backup.
+    pub paths: Vec<String>,
+    /// What happens to local files the backup would replace.
+    pub policy: OverwritePolicy,
+}
+
+pub struct RestorePlan {
+    pub bucket: String,
+    pub meta: BackupMeta,
+    pub target_path: PathBuf,
+    /// Globs the restore is limited to, see [`PullOptions::paths`].
+    pub paths: Vec<String>,
+    /// Backup files that will be extracted.
+    pub files: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub policy: OverwritePolicy,
+    /// Password for an encrypted backup, see [`BackupMeta::encrypted`].
+    pub password: Option<String>,
+    /// Earlier backups whose archives hold the unchanged files of an incremental backup that are
+    /// restored, see [`BackupFile::stored_in`](crate::utils::backup_meta::BackupFile::stored_in).
+    pub bases: Vec<BackupMeta>,
+}
+
+impl RestorePlan {
+    /// Bytes the restore downloads: the backup's archive and the archives of `bases`.
+    pub fn download_size(&self) -> u64 {
+        self.meta.archive_size + self.bases.iter().map(|b| b.archive_size).sum::<u64>()
+    }
+}
+
+pub async fn find_latest_backup(config: &ProjectConfig, client: &Client) -> anyhow::Result<BackupMeta> {
+    match latest_backup(config, client).await? {
+        Some(meta) => Ok(meta),
+        None => bail!("No backups found for project '{}'", config.project_name),
+    }
+}
+
+/// Like [`find_latest_backup`], `None` when the project has no backups yet.
+pub async fn latest_backup(config: &ProjectConfig, client: &Client) -> anyhow::Result<Option<BackupMeta>> {
+    let keys = tools::aws::list_keys(client, &config.bucket, &config.backups_prefix()).await?;
+
+    // Идентификаторы бэкапов - UTC таймстемпы, поэтому последний по ключу является самым свежим
+    match keys.into_iter().filter(|k| k.ends_with(".json")).max() {
+        Some(latest) => Ok(Some(load_meta(config, client, &latest).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Loads the metadata of backup `id`, or of the latest backup when `id` is `None`.
+pub async fn find_backup(config: &ProjectConfig, client: &Client, id: Option<&str>) -> anyhow::Result<BackupMeta> {
+    let Some(id) = id else {
+        return find_latest_backup(config, client).await;
+    };
+    let key = config.backup_meta_key(id);
+    if !tools::aws::object_exists(client, &config.bucket, &key).await? {
+        bail!("Backup '{}' not found for project '{}'", id, config.project_name);
+    }
+    load_meta(config, client, &key).await
+}
+
+/// Loads the earlier backups whose archives hold the unchanged files of `meta` that `wanted`
+/// accepts. Fails when one of them is gone, since those files can't be restored then.
+pub(crate) async fn find_bases(
+    config: &ProjectConfig,
+    client: &Client,
+    meta: &BackupMeta,
+    wanted: impl Fn(&BackupFile) -> bool,
+) -> anyhow::Result<Vec<BackupMeta>> {
+    let base_ids: BTreeSet<&str> = meta.files.iter().filter(|f| wanted(f)).filter_map(|f| f.stored_in.as_deref()).collect();
+    let mut bases = Vec::new();
+    for id in base_ids {
+        let base = find_backup(config, client, Some(id)).await;
+        bases.push(base.with_context(|| format!("Backup {} holds unchanged files of {}", id, meta.id))?);
+    }
+    Ok(bases)
+}
+
+pub(crate) async fn load_meta(config: &ProjectConfig, client: &Client, key: &str) -> anyhow::Result<BackupMeta> {
+    let data = tools::aws::get_bytes(client, &config.bucket, key).await?;
+    serde_json::from_slice(&data).with_context(|| format!("Failed to parse backup metadata '{}'", key))
+}
+
+pub async fn plan_restore(project_path: &Path, client: &Client, options: &PullOptions) -> anyhow::Result<RestorePlan> {
+    let config = ProjectConfig::load(project_path)?;
+    let selection = PathSelection::new(&options.paths)?;
+    let meta = find_backup(&config, client, options.backup_id.as_deref()).await?;
+
+    let files: Vec<String> = meta
+        .files
+        .iter()
+        .filter(|f| options.paths.is_empty() || selection.matches(&f.path))
+        .map(|f| f.path.clone())
+        .collect();
+    if files.is_empty() && !options.paths.is_empty() {
+        bail!("No files in backup {} match {}", meta.object_key, options.paths.join(", "));
+    }
+
+    let overwritten = files
+        .iter()
+        .filter(|path| project_path.join(path).is_file())
+        .cloned()
+        .collect();
+
+    let selected: HashSet<&str> = files.iter().map(String::as_str).collect();
+    let bases = find_bases(&config, client, &meta, |f| selected.contains(f.path.as_str())).await?;
+
+    Ok(RestorePlan {
+        bucket: config.bucket,
+        meta,
+        target_path: project_path.to_path_buf(),
+        paths: options.paths.clone(),
+        files,
+        overwritten,
+        policy: options.policy,
+        password: None,
+        bases,
+    })
+}
+
+/// Downloads the archive of `meta` to `archive_path` and compares its digest, and the digest of
+/// every volume of a split archive, with the metadata. Volumes are appended to the archive one at
+/// a time, so only one of them is on disk next to it. Returns the digests that don't match.
+pub async fn download_archive(
+    meta: &BackupMeta,
+    bucket: &str,
+    client: &Client,
+    archive_path: &Path,
+) -> anyhow::Result<Vec<ChecksumMismatch>> {
+    let mut mismatches = Vec::new();
+
+    let sha256 = if meta.volumes.is_empty() {
+        tools::aws::download_from_yandex_s3(client, archive_path, bucket, &meta.object_key).await?
+    } else {
+        let mut archive = fs::File::create(archive_path).context("Failed to create archive file")?;
+        let volume_path = archive_path.with_extension("volume");
+        for (index, volume) in meta.volumes.iter().enumerate() {
+            println!("Downloading volume {}/{}", index + 1, meta.volumes.len());
+            let sha256 = tools::aws::download_from_yandex_s3(client, &volume_path, bucket, &volume.key).await?;
+            if sha256 != volume.sha256 {
+                mismatches.push(ChecksumMismatch { key: volume.key.clone(), expected: volume.sha256.clone(), actual: sha256 });
+            }
+            io::copy(&mut fs::File::open(&volume_path)?, &mut archive)?;
+            fs::remove_file(&volume_path)?;
+        }
+        drop(archive);
+        tools::checksum::sha256_file(archive_path)?
+    };
+
+    if !meta.sha256.is_empty() && sha256 != meta.sha256 {
+        mismatches.push(ChecksumMismatch { key: meta.object_key.clone(), expected: meta.sha256.clone(), actual: sha256 });
+    }
+    Ok(mismatches)
+}
+
+pub async fn restore_backup(plan: RestorePlan, client: &Client) -> anyhow::Result<TransferSummary> {
+    if plan.meta.encrypted && plan.password.is_none() {
+        return Err(PasswordError::Required).with_context(|| format!("Failed to restore {}", plan.meta.object_key));
+    }
+
+    let start_time = Instant::now();
+    println!("Restoring backup {} to: {}", plan.meta.object_key, plan.target_path.display());
 
     let temp_dir = PathBuf::from("backup_temp");
     if !temp_dir.exists() {
         fs::create_dir(&temp_dir).context("Failed to create temp directory")?;
     }
 
-    let download_path = temp_dir.join(format!("UE5_Restore_{}.7z", project_name));
-    let bucket_name = env::var("BUCKET_NAME").context("BUCKET_NAME not set")?;
-    let object_key = format!("backups/{}.7z", project_name);
+    if plan.meta.storage == Storage::Chunks {
+        let report = restore_chunks(&plan, client).await?;
+        record_snapshot(&plan, &report)?;
+        return Ok(TransferSummary {
+            bytes: plan.download_size(),
+            object_key: plan.meta.object_key,
+            duration: start_time.elapsed(),
+            compression: None,
+            extraction: Some(report),
+            chunks: None,
+        });
+    }
+
+    let selected: HashSet<&str> = plan.files.iter().map(String::as_str).collect();
+    let mut report = ExtractReport::default();
+
+    // Unchanged files first and the backup's own archive last, so its links are created after every file
+    for base in &plan.bases {
+        println!("Restoring unchanged files from {}", base.object_key);
+        let entries: HashSet<String> = plan
+            .meta
+            .files
+            .iter()
+            .filter(|f| f.stored_in.as_deref() == Some(base.id.as_str()) && selected.contains(f.path.as_str()))
+            .map(|f| f.path.clone())
+            .collect();
+        let options = ExtractOptions {
+            password: plan.password.clone(),
+            total_bytes: Some(plan.meta.files.iter().filter(|f| entries.contains(&f.path)).map(|f| f.size).sum()),
+            entries: Some(entries),
+            policy: plan.policy,
+            ..Default::default()
+        };
+        report.merge(restore_archive(base, &plan.bucket, client, &temp_dir, &plan.target_path, &options).await?);
+    }
 
-    tools::aws::download_from_yandex_s3(&download_path, &bucket_name, &object_key).await?;
-    tools::compressing::extract_7z_archive(&download_path, target_path).await?;
+    let options = ExtractOptions {
+        password: plan.password.clone(),
+        selection: if plan.paths.is_empty() { None } else { Some(PathSelection::new(&plan.paths)?) },
+        entries: None,
+        policy: plan.policy,
+        total_bytes: Some(plan.meta.archived_files().filter(|f| selected.contains(f.path.as_str())).map(|f| f.size).sum()),
+    };
+    report.merge(restore_archive(&plan.meta, &plan.bucket, client, &temp_dir, &plan.target_path, &options).await?);
+    record_snapshot(&plan, &report)?;
+
+    Ok(TransferSummary {
+        bytes: plan.download_size(),
+        object_key: plan.meta.object_key,
+        duration: start_time.elapsed(),
+        compression: None,
+        extraction: Some(report),
+        chunks: None,
+    })
+}
+
+/// Records the restored snapshot for `status`. A pull limited to some paths, or one that kept local
+/// files in place of some entries, leaves a tree that is not the snapshot, so the snapshot synced
+/// before stays recorded.
+fn record_snapshot(plan: &RestorePlan, report: &ExtractReport) -> anyhow::Result<()> {
+    if !plan.paths.is_empty() || report.kept > 0 || report.renamed > 0 {
+        println!("Partial restore, 'status' keeps comparing with the snapshot synced before");
+        return Ok(());
+    }
+    LocalSnapshot::restored(&plan.meta, &plan.target_path).save(&plan.target_path)
+}
+
+/// Downloads the archive of `meta` into `temp_dir`, checks it and extracts it to `target_path`.
+async fn restore_archive(
+    meta: &BackupMeta,
+    bucket: &str,
+    client: &Client,
+    temp_dir: &Path,
+    target_path: &Path,
+    options: &ExtractOptions,
+) -> anyhow::Result<ExtractReport> {
+    let download_path = temp_dir.join(format!("UE5_Restore_{}.{}", meta.project_name, meta.format.extension()));
+
+    let mismatches = download_archive(meta, bucket, client, &download_path).await?;
+    if let Some(mismatch) = mismatches.first() {
+        fs::remove_file(&download_path).ok();
+        bail!("{}", mismatch);
+    }
+    let extracted = tools::compressing::extract_archive(&download_path, target_path, meta.format, options).await;
+    let report = match extracted {
+        Ok(report) => report,
+        Err(e) => {
+            fs::remove_file(&download_path).ok();
+            return Err(e.context(format!("Failed to restore {}", meta.object_key)));
+        }
+    };
     fs::remove_file(&download_path).context("Failed to remove temporary download file")?;
+    Ok(report)
+}
+
+/// Rebuilds the selected files of a backup in the chunk store from their chunks, then recreates
+/// its empty directories and links.
+async fn restore_chunks(plan: &RestorePlan, client: &Client) -> anyhow::Result<ExtractReport> {
+    let options = ExtractOptions {
+        selection: if plan.paths.is_empty() { None } else { Some(PathSelection::new(&plan.paths)?) },
+        policy: plan.policy,
+        ..Default::default()
+    };
+    let files: Vec<BackupFile> = plan.meta.files.iter().filter(|f| options.selects(&f.path)).cloned().collect();
+    let progress = Progress::new("Restoring", Some(files.iter().map(|f| f.size).sum()));
 
-    Ok(())
-}
\ No newline at end of file
+    let (meta, bucket, client, target) = (plan.meta.clone(), plan.bucket.clone(), client.clone(), plan.target_path.clone());
+    let task_progress = progress.clone();
+    let restored = tokio::task::spawn_blocking(move || {
+        fs::create_dir_all(&target).with_context(|| format!("Failed to create {}", target.display()))?;
+        let mut writer = EntryWriter::new(&target, options.policy, &task_progress);
+        for dir in meta.empty_dirs.iter().filter(|d| options.selects(d)) {
+            writer.create_dir(dir)?;
+        }
+        for file in &files {
+            let metadata = EntryMetadata {
+                modified: file.mtime_ns.map(|ns| UNIX_EPOCH + Duration::from_nanos(ns)),
+                mode: file.mode,
+            };
+            writer.write_file(&file.path, &metadata, &mut ChunkReader::new(&client, &bucket, &file.chunks, &task_progress))?;
+        }
+        for link in meta.links.iter().filter(|l| options.selects(&l.path)) {
+            writer.add_link(&link.path, &link.target)?;
+        }
+        writer.finish()
+    })
+    .await?;
+
+    match restored {
+        Ok(report) => {
+            progress.finish("Restore complete!");
+            println!("Backup successfully restored to: {}", plan.target_path.display());
+            Ok(report)
+        }
+        Err(e) => {
+            progress.abandon();
+            Err(e.context(format!("Failed to restore {}", plan.meta.object_key)))
+        }
+    }
+}
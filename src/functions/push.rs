@@ -1,34 +1,352 @@
-use std::{env, fs};
+use std::fs;
 use std::path::{Path, PathBuf};
-use anyhow::Context;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::{bail, Context};
+use aws_sdk_s3::Client;
+use chrono::{SecondsFormat, Utc};
+use sha2::{Digest, Sha256};
+use crate::functions::pull;
 use crate::tools;
-use dotenv::dotenv;
+use crate::tools::chunk_store::{ChunkReport, PackWriter, Storage};
+use crate::tools::compressing::{ArchiveFormat, CompressionMethod, CompressionReport, ExtractReport, ProjectFile, SymlinkPolicy};
+use crate::utils::backup_meta::{BackupLink, BackupMeta, BackupVolume, SNAPSHOT_ID_FORMAT};
+use crate::utils::local_state::LocalSnapshot;
+use crate::utils::password::PasswordCheck;
+use crate::utils::project_config::{BackupProfile, ProjectConfig};
+use crate::utils::user::User;
 
+#[derive(Debug)]
+pub struct TransferSummary {
+    pub object_key: String,
+    pub bytes: u64,
+    pub duration: Duration,
+    /// Set for pushes only.
+    pub compression: Option<CompressionReport>,
+    /// Set for pulls only.
+    pub extraction: Option<ExtractReport>,
+    /// Set for pushes to the chunk store only.
+    pub chunks: Option<ChunkReport>,
+}
 
-pub async fn create_backup(project_path: &Path) -> anyhow::Result<()> {
-    dotenv().ok();
+#[derive(Debug, Default)]
+pub struct PushOptions {
+    /// Name of a profile from the project config; `None` backs up everything not ignored.
+    pub profile: Option<String>,
+    /// Archive format; `None` uses the format from the project config.
+    pub format: Option<ArchiveFormat>,
+    /// Overrides for the compression settings from the project config.
+    pub method: Option<CompressionMethod>,
+    pub level: Option<u32>,
+    pub threads: Option<usize>,
+    /// Volume size in megabytes; `None` uses the project config.
+    pub volume_size_mb: Option<u64>,
+    /// Symlink policy; `None` uses the project config.
+    pub symlinks: Option<SymlinkPolicy>,
+    /// Archive or chunk store; `None` uses the project config.
+    pub storage: Option<Storage>,
+    /// Encrypts the archive with this password.
+    pub password: Option<String>,
+    /// Uploads every file instead of only the ones changed since the latest backup.
+    pub full: bool,
+    /// Message recorded with the snapshot, shown by `log`.
+    pub message: Option<String>,
+}
+
+pub struct BackupPlan {
+    pub object_key: String,
+    pub profile: Option<String>,
+    pub format: ArchiveFormat,
+    pub storage: Storage,
+    pub files: Vec<ProjectFile>,
+}
+
+impl BackupPlan {
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+pub fn plan_backup(project_path: &Path, options: &PushOptions) -> anyhow::Result<BackupPlan> {
+    let config = ProjectConfig::load(project_path)?;
+    let profile = resolve_profile(&config, options)?;
+    let symlinks = options.symlinks.unwrap_or(config.symlinks);
+    let files = tools::compressing::collect_project_files(project_path, &profile, symlinks)?;
+    let format = options.format.unwrap_or(config.format);
+    let storage = options.storage.unwrap_or(config.storage);
+    let object_key = match storage {
+        Storage::Archive => config.backup_key(&new_backup_id(), &new_upload_id(), format),
+        Storage::Chunks => config.backup_meta_key(&new_backup_id()),
+    };
+
+    Ok(BackupPlan {
+        object_key,
+        profile: options.profile.clone(),
+        format,
+        storage,
+        files,
+    })
+}
+
+pub async fn create_backup(project_path: &Path, client: &Client, options: &PushOptions) -> anyhow::Result<TransferSummary> {
     println!("Backup project at: {}", project_path.display());
 
+    let config = ProjectConfig::load(project_path)?;
+    let profile = resolve_profile(&config, options)?;
+    if let Some(name) = &options.profile {
+        println!("Using profile '{}'", name);
+    }
+    let start_time = Instant::now();
+
     let temp_dir = PathBuf::from("backup_temp");
     if !temp_dir.exists() {
         fs::create_dir(&temp_dir).context("Failed to create temp directory")?;
     }
 
-    let output_name = format!(
-        "UE5_Backup_{}.7z",
-        project_path.file_name().unwrap().to_str().unwrap()
-    );
-    let output_7z_path = temp_dir.join(output_name);
+    if options.storage.unwrap_or(config.storage) == Storage::Chunks {
+        return create_chunked_backup(project_path, client, options, config, profile, &temp_dir, start_time).await;
+    }
+
+    let format = options.format.unwrap_or(config.format);
+    let upload_id = new_upload_id();
+    let output_name = format!("UE5_Backup_{}_{}.{}", config.project_name, upload_id, format.extension());
+    let output_path = temp_dir.join(output_name);
+
+    let mut settings = config.compression.clone();
+    settings.method = options.method.or(settings.method);
+    settings.level = options.level.or(settings.level);
+    settings.threads = options.threads.or(settings.threads);
+    settings.password = options.password.clone();
+
+    let volume_size = options.volume_size_mb.or(config.volume_size_mb).map(|mb| mb * 1024 * 1024);
+
+    let symlinks = options.symlinks.unwrap_or(config.symlinks);
+    let contents = tools::compressing::collect_project_with_spinner(project_path, &profile, symlinks)?;
+
+    let latest = pull::latest_backup(&config, client).await?;
+    let parent = latest.as_ref().map(|meta| meta.id.clone());
+    let base = match latest.filter(|_| !options.full) {
+        // Every archive of a backup is opened with the one password pull asks for
+        Some(base) if base.encrypted != settings.password.is_some() => {
+            println!("Latest backup {} is {}, pushing a full backup", base.id, if base.encrypted { "encrypted" } else { "not encrypted" });
+            None
+        }
+        Some(base) if settings.password.as_deref().is_some_and(|p| !base.password_check.as_ref().is_some_and(|c| c.matches(p))) => {
+            println!("Latest backup {} is encrypted with another password, pushing a full backup", base.id);
+            None
+        }
+        Some(base) if base.storage != Storage::Archive => {
+            println!("Latest backup {} is in the chunk store, pushing a full backup", base.id);
+            None
+        }
+        base => base,
+    };
+    let local = LocalSnapshot::load(project_path).ok().flatten();
+    let (mut contents, diff, base) = tokio::task::spawn_blocking(move || {
+        let diff = tools::manifest::diff_manifest(&contents.files, base.as_ref(), local.as_ref())?;
+        anyhow::Ok((contents, diff, base))
+    })
+    .await??;
+    let mut changed = diff.changed.iter();
+    contents.files.retain(|_| *changed.next().unwrap());
+    if let Some(base) = &base {
+        println!("Incremental push on top of {}: {} of {} files changed", base.id, diff.changed_count(), diff.files.len());
+    }
+    let backup_id = next_snapshot_id(parent.as_deref()).await;
+    let object_key = config.backup_key(&backup_id, &upload_id, format);
+    let empty_dirs: Vec<_> = contents.empty_dirs.iter().map(|d| d.relative_path.clone()).collect();
+    let links: Vec<_> = contents.links.iter().map(|l| BackupLink { path: l.relative_path.clone(), target: l.target.clone() }).collect();
+
+    let archive = tools::compressing::compress_contents(contents, &output_path, format, &settings, volume_size).await?;
+    let mut files = diff.files;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let (sha256, volume_digests) = tools::checksum::sha256_volumes(&archive.volumes)?;
+
+    let mut bytes = 0;
+    let mut volumes = Vec::new();
+    for (index, (path, digest)) in archive.volumes.iter().zip(volume_digests).enumerate() {
+        let size = fs::metadata(path)?.len();
+        bytes += size;
+        if volume_size.is_some() {
+            let key = format!("{}.{:03}", object_key, index + 1);
+            println!("Uploading volume {}/{}", index + 1, archive.volumes.len());
+            tools::aws::upload_to_yandex_s3(client, path, &config.bucket, &key).await?;
+            volumes.push(BackupVolume { key, size, sha256: digest });
+        } else {
+            tools::aws::upload_to_yandex_s3(client, path, &config.bucket, &object_key).await?;
+        }
+        fs::remove_file(path).context("Failed to remove temporary backup file")?;
+    }
+
+    let meta = BackupMeta {
+        id: backup_id,
+        project_name: config.project_name.clone(),
+        object_key: object_key.clone(),
+        archive_size: bytes,
+        format,
+        sha256,
+        created_by: User::get_user_name(),
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        parent,
+        message: options.message.clone(),
+        tree_hash: tools::manifest::tree_hash(&files, &empty_dirs, &links),
+        profile: options.profile.clone(),
+        profile_include: profile.include,
+        symlinks: Some(symlinks),
+        volumes,
+        encrypted: archive.report.encrypted,
+        password_check: settings.password.as_deref().map(PasswordCheck::new),
+        base: base.map(|b| b.id),
+        storage: Storage::Archive,
+        files,
+        empty_dirs: Vec::new(),
+        links: Vec::new(),
+    };
+    put_snapshot(client, &config, &meta).await?;
+    LocalSnapshot::from_meta(&meta, "push").save(project_path)?;
+
+    Ok(TransferSummary {
+        object_key,
+        bytes,
+        duration: start_time.elapsed(),
+        compression: Some(archive.report),
+        extraction: None,
+        chunks: None,
+    })
+}
+
+/// Pushes to the bucket's chunk store: files changed since the latest backup are chunked, and
+/// only the chunks no backup of any project in the bucket has stored yet are uploaded, in packs.
+async fn create_chunked_backup(
+    project_path: &Path,
+    client: &Client,
+    options: &PushOptions,
+    config: ProjectConfig,
+    profile: BackupProfile,
+    temp_dir: &Path,
+    start_time: Instant,
+) -> anyhow::Result<TransferSummary> {
+    if options.password.is_some() {
+        bail!("Backups in the chunk store can't be encrypted, push with --storage archive");
+    }
 
-    let bucket_name = env::var("BUCKET_NAME").context("BUCKET_NAME not set")?;
-    let object_key = format!(
-        "backups/{}.7z",
-        project_path.file_name().unwrap().to_str().unwrap()
+    let symlinks = options.symlinks.unwrap_or(config.symlinks);
+    let contents = tools::compressing::collect_project_with_spinner(project_path, &profile, symlinks)?;
+
+    let latest = pull::latest_backup(&config, client).await?;
+    let parent = latest.as_ref().map(|meta| meta.id.clone());
+    let base = match latest.filter(|_| !options.full) {
+        Some(base) if base.storage != Storage::Chunks => {
+            println!("Latest backup {} is an archive, chunking every file", base.id);
+            None
+        }
+        base => base,
+    };
+    let known = tools::chunk_store::load_index(client, &config.bucket, project_path).await?;
+
+    let local = LocalSnapshot::load(project_path).ok().flatten();
+    let pack_dir = temp_dir.to_path_buf();
+    let (contents, mut files, packs, report, base) = tokio::task::spawn_blocking(move || {
+        let mut packs = PackWriter::new(&pack_dir, known);
+        let files = tools::manifest::chunk_manifest(&contents.files, base.as_ref(), local.as_ref(), &mut packs)?;
+        let (packs, report) = packs.finish()?;
+        anyhow::Ok((contents, files, packs, report, base))
+    })
+    .await??;
+    println!(
+        "{} of {} chunks are new ({} of {} files read)",
+        report.new_chunks, report.chunks, report.chunked_files, files.len()
     );
 
-    tools::compressing::compress_project_to_7z(project_path, &output_7z_path).await?;
-    tools::aws::upload_to_yandex_s3(&output_7z_path, &bucket_name, &object_key).await?;
-    fs::remove_file(&output_7z_path).context("Failed to remove temporary backup file")?;
+    let backup_id = next_snapshot_id(parent.as_deref()).await;
+    let object_key = config.backup_meta_key(&backup_id);
+
+    let bytes = tools::chunk_store::upload_packs(client, &config.bucket, packs, project_path).await?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let empty_dirs: Vec<_> = contents.empty_dirs.into_iter().map(|d| d.relative_path).collect();
+    let links: Vec<_> = contents.links.into_iter().map(|l| BackupLink { path: l.relative_path, target: l.target }).collect();
+
+    let mut meta = BackupMeta {
+        id: backup_id,
+        project_name: config.project_name.clone(),
+        object_key: object_key.clone(),
+        archive_size: 0,
+        format: ArchiveFormat::default(),
+        sha256: String::new(),
+        created_by: User::get_user_name(),
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        parent,
+        message: options.message.clone(),
+        tree_hash: tools::manifest::tree_hash(&files, &empty_dirs, &links),
+        profile: options.profile.clone(),
+        profile_include: profile.include,
+        symlinks: Some(symlinks),
+        volumes: Vec::new(),
+        encrypted: false,
+        password_check: None,
+        base: base.map(|b| b.id),
+        storage: Storage::Chunks,
+        files,
+        empty_dirs,
+        links,
+    };
+    meta.archive_size = meta.unique_chunks().map(|c| c.length).sum();
+    put_snapshot(client, &config, &meta).await?;
+    LocalSnapshot::from_meta(&meta, "push").save(project_path)?;
 
+    Ok(TransferSummary {
+        object_key,
+        bytes,
+        duration: start_time.elapsed(),
+        compression: None,
+        extraction: None,
+        chunks: Some(report),
+    })
+}
+
+fn resolve_profile(config: &ProjectConfig, options: &PushOptions) -> anyhow::Result<BackupProfile> {
+    match &options.profile {
+        Some(name) => Ok(config.profile(name)?.clone()),
+        None => Ok(BackupProfile::default()),
+    }
+}
+
+fn new_backup_id() -> String {
+    Utc::now().format(SNAPSHOT_ID_FORMAT).to_string()
+}
+
+/// Id for a snapshot on top of `parent`. Ids are timestamps with one-second resolution and the
+/// latest snapshot is the one with the greatest id, so a push within the same second as its
+/// parent waits for the next one.
+async fn next_snapshot_id(parent: Option<&str>) -> String {
+    let mut id = new_backup_id();
+    while parent.is_some_and(|parent| id.as_str() <= parent) {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        id = new_backup_id();
+    }
+    id
+}
+
+/// Suffix that keeps the data keys of this push apart from those of any other push, including
+/// one from another machine that gets the same snapshot id.
+fn new_upload_id() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let seed = format!("{}:{}:{}", User::get_user_name(), std::process::id(), nanos);
+    hex::encode(&Sha256::digest(seed.as_bytes())[..6])
+}
+
+/// Uploads the metadata of a new snapshot. Snapshots are immutable: the metadata is written with a
+/// conditional put, so one that already exists, e.g. pushed from another machine within the same
+/// second, is never replaced. The data of the losing push sits under its own upload id and is left
+/// unreferenced.
+async fn put_snapshot(client: &Client, config: &ProjectConfig, meta: &BackupMeta) -> anyhow::Result<()> {
+    let key = config.backup_meta_key(&meta.id);
+    let data = serde_json::to_vec(meta)?;
+    if tools::aws::put_bytes_if_absent(client, &config.bucket, &key, data.clone()).await? {
+        return Ok(());
+    }
+    // A retried put whose first attempt went through finds its own metadata
+    if tools::aws::get_bytes(client, &config.bucket, &key).await? != data {
+        bail!("Snapshot {} already exists, push again to create a new one", meta.id);
+    }
     Ok(())
-}
\ No newline at end of file
+}
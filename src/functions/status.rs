@@ -0,0 +1,132 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context};
+use crate::tools;
+use crate::tools::checksum;
+use crate::tools::progress::Progress;
+use crate::utils::local_state::{HashCache, LocalSnapshot, TrackedFile};
+use crate::utils::project_config::{BackupProfile, ProjectConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Size in the working tree; `None` for a deleted file.
+    pub size: Option<u64>,
+    /// Size in the snapshot; `None` for an added file.
+    pub snapshot_size: Option<u64>,
+}
+
+/// Differences between the working tree and the snapshot last pushed or pulled on this machine.
+pub struct StatusReport {
+    pub snapshot: LocalSnapshot,
+    /// Changed files sorted by path.
+    pub changes: Vec<FileChange>,
+    pub unchanged: usize,
+    /// Bytes read to tell touched files from modified ones; the others were answered from sizes,
+    /// mtimes and the hash cache.
+    pub hashed_bytes: u64,
+}
+
+impl StatusReport {
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.kind == kind).count()
+    }
+}
+
+/// Compares the project tree, with the excludes, the profile and the symlink policy of the
+/// snapshot applied, against the manifest of the snapshot last pushed or pulled here, without
+/// contacting the bucket. A file is only read when its size matches the snapshot but its mtime
+/// doesn't, and the hash is cached in `.rsget/hash_cache.json` for the next run.
+pub fn project_status(project_path: &Path) -> anyhow::Result<StatusReport> {
+    let config = ProjectConfig::load(project_path)?;
+    let Some(snapshot) = LocalSnapshot::load(project_path)? else {
+        bail!("Nothing was pushed or pulled on this machine yet, run 'push' or 'pull' first");
+    };
+    let profile = match &snapshot.profile {
+        Some(name) => config.profile(name).with_context(|| format!("Snapshot {} was pushed with profile '{}'", snapshot.id, name))?.clone(),
+        None => BackupProfile::default(),
+    };
+    let symlinks = snapshot.symlinks.unwrap_or(config.symlinks);
+    let files = tools::compressing::collect_project_with_spinner(project_path, &profile, symlinks)?.files;
+
+    let tracked: HashMap<&str, &TrackedFile> = snapshot.files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let cached: HashMap<String, TrackedFile> = HashCache::load(project_path).files.into_iter().map(|f| (f.path.clone(), f)).collect();
+    let mtimes = files
+        .iter()
+        .map(|f| Ok(fs::metadata(&f.path)?.modified().ok().and_then(tools::manifest::mtime_ns)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Only files that may have been touched without changing are read
+    let needs_hash = |path: &str, size: u64, mtime_ns: Option<u64>| {
+        tracked.get(path).is_some_and(|t| t.size == size && !t.matches(size, mtime_ns) && !t.sha256.is_empty())
+            && !cached.get(path).is_some_and(|c| c.matches(size, mtime_ns))
+    };
+    let to_hash = files.iter().zip(&mtimes).filter(|(f, m)| needs_hash(&f.relative_path, f.size, **m)).map(|(f, _)| f.size).sum();
+    let progress = Progress::new("Hashing", Some(to_hash));
+
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    let mut cache = HashCache::default();
+    for (file, mtime_ns) in files.iter().zip(mtimes) {
+        let path = file.relative_path.as_str();
+        let change = |kind| FileChange {
+            path: path.to_string(),
+            kind,
+            size: Some(file.size),
+            snapshot_size: tracked.get(path).map(|t| t.size),
+        };
+        let Some(snapshot_file) = tracked.get(path) else {
+            changes.push(change(ChangeKind::Added));
+            continue;
+        };
+        if snapshot_file.matches(file.size, mtime_ns) {
+            unchanged += 1;
+            continue;
+        }
+        if snapshot_file.size != file.size || snapshot_file.sha256.is_empty() {
+            changes.push(change(ChangeKind::Modified));
+            continue;
+        }
+
+        let sha256 = match cached.get(path).filter(|c| c.matches(file.size, mtime_ns)) {
+            Some(cached) => cached.sha256.clone(),
+            None => {
+                let source = fs::File::open(&file.path).with_context(|| format!("Failed to open {}", file.path.display()))?;
+                checksum::sha256_reader(&mut progress.reader(path, source)).with_context(|| format!("Failed to read {}", file.path.display()))?
+            }
+        };
+        if sha256 == snapshot_file.sha256 {
+            unchanged += 1;
+        } else {
+            changes.push(change(ChangeKind::Modified));
+        }
+        cache.files.push(TrackedFile { path: path.to_string(), size: file.size, mtime_ns, sha256 });
+    }
+    progress.finish("Hashing complete!");
+
+    let present: HashSet<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+    changes.extend(snapshot.files.iter().filter(|f| !present.contains(f.path.as_str())).map(|f| FileChange {
+        path: f.path.clone(),
+        kind: ChangeKind::Deleted,
+        size: None,
+        snapshot_size: Some(f.size),
+    }));
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    cache.save(project_path)?;
+
+    Ok(StatusReport {
+        snapshot,
+        changes,
+        unchanged,
+        hashed_bytes: to_hash,
+    })
+}
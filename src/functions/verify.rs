@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context};
+use aws_sdk_s3::Client;
+use crate::functions::pull;
+use crate::tools;
+use crate::tools::checksum::ChecksumMismatch;
+use crate::tools::chunk_store::{ChunkReader, Storage};
+use crate::tools::compressing::{ExtractOptions, PasswordError, VerifyReport};
+use crate::tools::progress::Progress;
+use crate::utils::backup_meta::{BackupFile, BackupMeta};
+use crate::utils::project_config::ProjectConfig;
+
+#[derive(Debug, Default)]
+pub struct VerifyOptions {
+    /// Backup to check; the latest one when `None`.
+    pub backup_id: Option<String>,
+    /// Local copy of the archive to check instead of downloading it.
+    pub archive: Option<PathBuf>,
+}
+
+pub struct VerifyPlan {
+    pub bucket: String,
+    pub meta: BackupMeta,
+    pub archive: Option<PathBuf>,
+    /// Password for an encrypted backup, see [`BackupMeta::encrypted`].
+    pub password: Option<String>,
+    /// Earlier backups holding the unchanged files of an incremental backup, see
+    /// [`BackupFile::stored_in`].
+    pub bases: Vec<BackupMeta>,
+}
+
+impl VerifyPlan {
+    /// Whether the backup or one of its bases is encrypted.
+    pub fn needs_password(&self) -> bool {
+        self.meta.encrypted || self.bases.iter().any(|b| b.encrypted)
+    }
+}
+
+/// Everything `verify_backup` found wrong with a backup; empty lists mean it restores as pushed.
+pub struct VerifySummary {
+    pub object_key: String,
+    /// Archives of the earlier backups the unchanged files were checked in.
+    pub bases: Vec<String>,
+    pub duration: Duration,
+    /// Archive and volume digests that differ from the metadata.
+    pub mismatches: Vec<ChecksumMismatch>,
+    pub report: VerifyReport,
+    /// Files the metadata lists in this backup's archive or its bases' that weren't decoded:
+    /// absent from the archive, or behind damage that stopped decoding.
+    pub missing: Vec<String>,
+}
+
+impl VerifySummary {
+    pub fn is_intact(&self) -> bool {
+        self.mismatches.is_empty() && self.report.corrupt.is_empty() && self.missing.is_empty()
+    }
+}
+
+pub async fn plan_verify(project_path: &Path, client: &Client, options: &VerifyOptions) -> anyhow::Result<VerifyPlan> {
+    let config = ProjectConfig::load(project_path)?;
+    let meta = pull::find_backup(&config, client, options.backup_id.as_deref()).await?;
+    let bases = pull::find_bases(&config, client, &meta, |_| true).await?;
+
+    if let Some(archive) = options.archive.as_ref().filter(|a| !a.is_file()) {
+        bail!("Archive not found: {}", archive.display());
+    }
+    if options.archive.is_some() && meta.storage == Storage::Chunks {
+        bail!("Backup {} is in the chunk store, it has no archive to compare a local copy with", meta.id);
+    }
+
+    Ok(VerifyPlan {
+        bucket: config.bucket,
+        meta,
+        archive: options.archive.clone(),
+        password: None,
+        bases,
+    })
+}
+
+/// Decodes every entry of the backup without writing it to disk, checking the archive digest
+/// against the metadata and the checksums stored in the archive. An incremental backup also has
+/// the unchanged files it takes from its bases checked, since a pull needs those too.
+pub async fn verify_backup(plan: VerifyPlan, client: &Client) -> anyhow::Result<VerifySummary> {
+    if plan.needs_password() && plan.password.is_none() {
+        return Err(PasswordError::Required).with_context(|| format!("Failed to verify {}", plan.meta.object_key));
+    }
+
+    let start_time = Instant::now();
+    if plan.meta.storage == Storage::Chunks {
+        return verify_chunks(plan, client, start_time).await;
+    }
+
+    let mut summary = VerifySummary {
+        object_key: plan.meta.object_key.clone(),
+        bases: plan.bases.iter().map(|b| b.object_key.clone()).collect(),
+        duration: Duration::ZERO,
+        mismatches: Vec::new(),
+        report: VerifyReport::default(),
+        missing: Vec::new(),
+    };
+    let archived: Vec<&BackupFile> = plan.meta.archived_files().collect();
+    check_archive(&plan, &plan.meta, plan.archive.as_deref(), &archived, None, client, &mut summary).await?;
+
+    for base in &plan.bases {
+        println!("Verifying unchanged files from {}", base.object_key);
+        let unchanged: Vec<&BackupFile> = plan.meta.files.iter().filter(|f| f.stored_in.as_deref() == Some(base.id.as_str())).collect();
+        let entries = unchanged.iter().map(|f| f.path.clone()).collect();
+        check_archive(&plan, base, None, &unchanged, Some(entries), client, &mut summary).await?;
+    }
+
+    summary.duration = start_time.elapsed();
+    Ok(summary)
+}
+
+/// Decodes the archive of `meta`, the backup's own or one of its bases, from `local` or from the
+/// bucket, limited to `entries` when given. `expected` are the files the backup takes from it.
+async fn check_archive(
+    plan: &VerifyPlan,
+    meta: &BackupMeta,
+    local: Option<&Path>,
+    expected: &[&BackupFile],
+    entries: Option<HashSet<String>>,
+    client: &Client,
+    summary: &mut VerifySummary,
+) -> anyhow::Result<()> {
+    let (archive_path, downloaded) = match local {
+        Some(archive) => {
+            println!("Verifying local copy {} of {}", archive.display(), meta.object_key);
+            let sha256 = tools::checksum::sha256_file(archive)?;
+            if !meta.sha256.is_empty() && sha256 != meta.sha256 {
+                summary.mismatches.push(ChecksumMismatch { key: archive.display().to_string(), expected: meta.sha256.clone(), actual: sha256 });
+            }
+            (archive.to_path_buf(), false)
+        }
+        None => {
+            println!("Verifying backup {}", meta.object_key);
+            let temp_dir = PathBuf::from("backup_temp");
+            if !temp_dir.exists() {
+                fs::create_dir(&temp_dir).context("Failed to create temp directory")?;
+            }
+            let download_path = temp_dir.join(format!("UE5_Verify_{}_{}.{}", meta.project_name, meta.id, meta.format.extension()));
+            summary.mismatches.extend(pull::download_archive(meta, &plan.bucket, client, &download_path).await?);
+            (download_path, true)
+        }
+    };
+
+    let options = ExtractOptions {
+        password: plan.password.clone(),
+        entries,
+        total_bytes: Some(expected.iter().map(|f| f.size).sum()),
+        ..Default::default()
+    };
+    let verified = tools::compressing::verify_archive(&archive_path, meta.format, &options).await;
+    if downloaded {
+        fs::remove_file(&archive_path).ok();
+    }
+    let report = verified.with_context(|| format!("Failed to verify {}", meta.object_key))?;
+
+    let decoded: HashSet<&str> = report
+        .verified
+        .iter()
+        .map(String::as_str)
+        .chain(report.corrupt.iter().filter_map(|c| c.name.as_deref()))
+        .collect();
+    summary.missing.extend(expected.iter().filter(|f| !decoded.contains(f.path.as_str())).map(|f| f.path.clone()));
+    summary.report.merge(report);
+    Ok(())
+}
+
+/// Rebuilds every file of a backup in the chunk store without writing it, checking each chunk
+/// against its hash.
+async fn verify_chunks(plan: VerifyPlan, client: &Client, start_time: Instant) -> anyhow::Result<VerifySummary> {
+    println!("Verifying backup {} in the chunk store", plan.meta.object_key);
+
+    let progress = Progress::new("Verifying", Some(plan.meta.archived_size()));
+    let (meta, bucket, client) = (plan.meta.clone(), plan.bucket.clone(), client.clone());
+    let task_progress = progress.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let mut report = VerifyReport::default();
+        for file in &meta.files {
+            report.check(&file.path, &mut ChunkReader::new(&client, &bucket, &file.chunks, &task_progress), &task_progress);
+        }
+        report
+    })
+    .await?;
+    progress.finish("Verification complete!");
+
+    Ok(VerifySummary {
+        object_key: plan.meta.object_key,
+        bases: Vec::new(),
+        duration: start_time.elapsed(),
+        mismatches: Vec::new(),
+        report,
+        missing: Vec::new(),
+    })
+}
@@ -0,0 +1,3 @@
+pub mod tools;
+pub mod functions;
+pub mod utils;
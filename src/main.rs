@@ -1,19 +1,27 @@
 use std::{io, thread};
+use std::path::Path;
 use std::process::Command;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
-use anyhow::{ Result};
+use anyhow::{bail, Context, Result};
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
-use crate::utils::input::MyHelper;
-use crate::utils::prints::Prints;
-use crate::utils::project::Project;
-use crate::utils::router::Router;
-use crate::utils::unreal_engine::Engine;
-
-mod tools;
-mod functions;
-mod utils;
+use rustyline::history::FileHistory;
+use rsget::{functions, tools};
+use rsget::functions::list::ListOptions;
+use rsget::functions::log::LogOptions;
+use rsget::functions::pull::PullOptions;
+use rsget::functions::push::PushOptions;
+use rsget::functions::verify::VerifyOptions;
+use rsget::utils::args::{split_line, CommandArgs};
+use rsget::utils::backup_meta::BackupMeta;
+use rsget::utils::input::MyHelper;
+use rsget::utils::password;
+use rsget::utils::prints::Prints;
+use rsget::utils::project::Project;
+use rsget::utils::router::Router;
+use rsget::utils::unreal_engine::Engine;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     Prints::print_logo();
@@ -44,31 +52,108 @@ async fn main() -> Result<()> {
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str())?;
-                let parts: Vec<&str> = line.split_whitespace().collect();
+                let words = match split_line(&line) {
+                    Ok(words) => words,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                let parts: Vec<&str> = words.iter().map(String::as_str).collect();
                 match parts.as_slice() {
                     ["path"] => {
                         get_all_files_in_dir().await?;
                     },
-                    ["init"] => {
+                    ["init"] | ["init", "--create-bucket"] => {
                         if *current_project.unwrap() != Project::default() {
-                            // checking project conf
-                            println!("Init project, created config, checking aws storage, init your branch.....");
+                            let create_bucket = parts.len() == 2;
+                            if let Err(e) = init_project(current_project.unwrap(), create_bucket).await {
+                                println!("Init failed: {:#}", e);
+                            }
+                        } else {
+                            println!("Select project to use init command... ");
                         }
                     },
-                    ["pull"] => {
+                    ["pull", args @ ..] => {
                         if *current_project.unwrap() != Project::default() {
-                            println!("Pulling project {}....", &current_project.unwrap().name);
+                            match CommandArgs::parse(args, &["--dry-run"], &["--overwrite"]) {
+                                Ok(args) => {
+                                    if let Err(e) = pull_project(current_project.unwrap(), &args, &mut rl).await {
+                                        println!("Pull failed: {:#}", e);
+                                    }
+                                }
+                                Err(e) => println!("{}", e),
+                            }
                         } else {
                             println!("Select project to use pull command... ");
                         }
                     },
-                    ["push"] => {
+                    ["push", args @ ..] => {
                         if *current_project.unwrap() != Project::default()   {
-                            println!("Pushing project {}....", &current_project.unwrap().name);
+                            match CommandArgs::parse(args, &["--dry-run", "--encrypt", "--full"], &["--profile", "--format", "--method", "--level", "--threads", "--volume-size", "--symlinks", "--storage", "--message"]) {
+                                Ok(args) => {
+                                    if let Err(e) = push_project(current_project.unwrap(), &args).await {
+                                        println!("Push failed: {:#}", e);
+                                    }
+                                }
+                                Err(e) => println!("{}", e),
+                            }
                         } else {
                             println!("Select project to use push command... ");
                         }
                     },
+                    ["status"] => {
+                        if *current_project.unwrap() != Project::default() {
+                            match functions::status::project_status(Path::new(&current_project.unwrap().path)) {
+                                Ok(report) => Prints::print_status(&report),
+                                Err(e) => println!("Status failed: {:#}", e),
+                            }
+                        } else {
+                            println!("Select project to use status command... ");
+                        }
+                    },
+                    ["log", args @ ..] => {
+                        if *current_project.unwrap() != Project::default() {
+                            match CommandArgs::parse(args, &[], &["--limit"]) {
+                                Ok(args) => {
+                                    if let Err(e) = show_log(current_project.unwrap(), &args).await {
+                                        println!("Log failed: {:#}", e);
+                                    }
+                                }
+                                Err(e) => println!("{}", e),
+                            }
+                        } else {
+                            println!("Select project to use log command... ");
+                        }
+                    },
+                    ["ls", args @ ..] => {
+                        if *current_project.unwrap() != Project::default() {
+                            match CommandArgs::parse(args, &["--tree"], &[]) {
+                                Ok(args) => {
+                                    if let Err(e) = list_backup(current_project.unwrap(), &args).await {
+                                        println!("Ls failed: {:#}", e);
+                                    }
+                                }
+                                Err(e) => println!("{}", e),
+                            }
+                        } else {
+                            println!("Select project to use ls command... ");
+                        }
+                    },
+                    ["verify", args @ ..] => {
+                        if *current_project.unwrap() != Project::default() {
+                            match CommandArgs::parse(args, &[], &["--archive"]) {
+                                Ok(args) => {
+                                    if let Err(e) = verify_backup(current_project.unwrap(), &args).await {
+                                        println!("Verify failed: {:#}", e);
+                                    }
+                                }
+                                Err(e) => println!("{}", e),
+                            }
+                        } else {
+                            println!("Select project to use verify command... ");
+                        }
+                    },
                     ["set", name] => {
                         if *current_project.unwrap() != Project::default() {
                             println!("Current project {}, use 'unset' to disable project", &current_project.unwrap().name);
@@ -90,7 +175,9 @@ async fn main() -> Result<()> {
                     ["start"] => {
                         if *current_project.unwrap() != Project::default()   {
                             let engine = "C:\\Program Files\\Epic Games\\UE_5.5\\Engine\\Binaries\\Win64\\UnrealEditor.exe";
-                            Command::new(engine).spawn().expect("TODO: panic message");
+                            if let Err(e) = Command::new(engine).spawn() {
+                                println!("Failed to start Unreal Editor: {}", e);
+                            }
                         } else {
                             println!("Select project to use start command... ");
                         }
@@ -141,4 +228,132 @@ async fn get_all_files_in_dir() -> Result<Vec<String>> {
     Ok(files)
 }
 
+async fn init_project(project: &Project, create_bucket: bool) -> Result<()> {
+    let client = tools::aws::create_client()?;
+    functions::init::init_project(project, create_bucket, &client).await?;
+    Ok(())
+}
+
+async fn push_project(project: &Project, args: &CommandArgs<'_>) -> Result<()> {
+    if !args.positional.is_empty() {
+        bail!("Unexpected arguments: {}, quote a message with spaces", args.positional.join(" "));
+    }
+    let message = args.value("--message").map(str::to_string);
+    let mut options = PushOptions {
+        profile: args.value("--profile").map(str::to_string),
+        format: args.value("--format").map(str::parse).transpose()?,
+        method: args.value("--method").map(str::parse).transpose()?,
+        level: args.value("--level").map(str::parse).transpose().context("Compression level must be a number")?,
+        threads: args.value("--threads").map(str::parse).transpose().context("Thread count must be a number")?,
+        volume_size_mb: args.value("--volume-size").map(str::parse).transpose().context("Volume size must be a number of megabytes")?,
+        symlinks: args.value("--symlinks").map(str::parse).transpose()?,
+        storage: args.value("--storage").map(str::parse).transpose()?,
+        password: None,
+        full: args.flag("--full"),
+        message,
+    };
+
+    if args.flag("--dry-run") {
+        let plan = functions::push::plan_backup(Path::new(&project.path), &options)?;
+        Prints::print_backup_plan(&plan);
+        return Ok(());
+    }
+
+    if args.flag("--encrypt") {
+        options.password = Some(password::read_password(true)?);
+    }
+
+    let client = tools::aws::create_client()?;
+    let summary = functions::push::create_backup(Path::new(&project.path), &client, &options).await?;
+    Prints::print_transfer_summary("Push", &summary);
+    Ok(())
+}
 
+async fn pull_project(project: &Project, args: &CommandArgs<'_>, rl: &mut Editor<MyHelper, FileHistory>) -> Result<()> {
+    // A leading snapshot id picks the snapshot, everything else is a path glob
+    let (backup, paths) = match args.positional.split_first() {
+        Some((first, paths)) if *first == "latest" || BackupMeta::is_id(first) => (Some(*first), paths),
+        _ => (None, &args.positional[..]),
+    };
+    let options = PullOptions {
+        backup_id: backup.filter(|id| *id != "latest").map(str::to_string),
+        paths: paths.iter().map(|p| p.to_string()).collect(),
+        policy: args.value("--overwrite").map(str::parse).transpose()?.unwrap_or_default(),
+    };
+
+    let client = tools::aws::create_client()?;
+    let mut plan = functions::pull::plan_restore(Path::new(&project.path), &client, &options).await?;
+    let editor_running = !Engine::find_ue_process().is_empty();
+    Prints::print_restore_plan(&plan, editor_running);
+
+    if args.flag("--dry-run") {
+        println!("Dry run: nothing was downloaded");
+        return Ok(());
+    }
+
+    let answer = rl.readline("Continue? [y/N] ").unwrap_or_default();
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Pull cancelled");
+        return Ok(());
+    }
+
+    if plan.meta.encrypted {
+        plan.password = Some(password::read_password(false)?);
+    }
+
+    let summary = functions::pull::restore_backup(plan, &client).await?;
+    Prints::print_transfer_summary("Pull", &summary);
+    Ok(())
+}
+
+async fn verify_backup(project: &Project, args: &CommandArgs<'_>) -> Result<()> {
+    if args.positional.len() > 1 {
+        bail!("Expected at most one backup id, got: {}", args.positional.join(" "));
+    }
+    let options = VerifyOptions {
+        backup_id: args.positional.first().map(|id| id.to_string()),
+        archive: args.value("--archive").map(Into::into),
+    };
+
+    let client = tools::aws::create_client()?;
+    let mut plan = functions::verify::plan_verify(Path::new(&project.path), &client, &options).await?;
+    if plan.needs_password() {
+        plan.password = Some(password::read_password(false)?);
+    }
+
+    let summary = functions::verify::verify_backup(plan, &client).await?;
+    Prints::print_verify_summary(&summary);
+    Ok(())
+}
+
+async fn show_log(project: &Project, args: &CommandArgs<'_>) -> Result<()> {
+    if !args.positional.is_empty() {
+        bail!("Unexpected arguments: {}", args.positional.join(" "));
+    }
+    let options = LogOptions {
+        limit: args.value("--limit").map(str::parse).transpose().context("Limit must be a number")?,
+    };
+
+    let client = tools::aws::create_client()?;
+    let snapshots = functions::log::snapshot_log(Path::new(&project.path), &client, &options).await?;
+    Prints::print_snapshot_log(&snapshots);
+    Ok(())
+}
+
+async fn list_backup(project: &Project, args: &CommandArgs<'_>) -> Result<()> {
+    let (backup, paths) = args.positional.split_first().map_or((None, &[][..]), |(backup, paths)| (Some(*backup), paths));
+    let options = ListOptions {
+        backup_id: backup.filter(|id| *id != "latest").map(str::to_string),
+        paths: paths.iter().map(|p| p.to_string()).collect(),
+    };
+
+    let client = tools::aws::create_client()?;
+    let mut plan = functions::list::plan_list(Path::new(&project.path), &client, &options).await?;
+    if plan.needs_password() {
+        plan.password = Some(password::read_password(false)?);
+    }
+
+    let listing = functions::list::list_backup(plan, &client).await?;
+    Prints::print_backup_listing(&listing, args.flag("--tree"));
+    Ok(())
+}
@@ -1,58 +1,78 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use aws_sdk_s3::{
-    config::{Credentials, Region},
+    config::{Credentials, Region, RequestChecksumCalculation, ResponseChecksumValidation},
     Client,
 };
-use indicatif::{ProgressBar, ProgressStyle};
-use std::{env, path::{Path}, time::{Instant}};
-use aws_sdk_s3::types::CompletedMultipartUpload;
+use crate::tools::progress::Progress;
+use sha2::{Digest, Sha256};
+use std::{env, path::{Path}, time::Duration};
+use aws_sdk_s3::config::retry::RetryConfig;
+use aws_sdk_s3::config::timeout::TimeoutConfig;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
 use tokio::{
     fs::File,
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 use dotenv::dotenv;
 
-pub async fn upload_to_yandex_s3(
-    file_path: &Path,
-    bucket_name: &str,
-    object_key: &str,
-) -> Result<()> {
+/// Attempts per request, shared by the SDK retry policy and download resumption.
+pub const MAX_ATTEMPTS: u32 = 5;
+/// How long an attempt waits to connect before it is retried.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an attempt waits for the response headers before it is retried. The request body is
+/// sent within it too, so it leaves an 8MB part about 2 Mbit/s; stalled response bodies are
+/// caught by the SDK's stalled stream protection.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn create_client() -> Result<Client> {
     dotenv().ok();
-    println!("Starting upload to Yandex S3...");
 
-    let access_key = env::var("YANDEX_ACCESS_KEY_ID")?;
-    let secret_key = env::var("YANDEX_SECRET_ACCESS_KEY_ID")?;
-    let endpoint = env::var("AWS_ENDPOINT")?;
-    let region = env::var("AWS_REGION")?;
+    let access_key = env::var("YANDEX_ACCESS_KEY_ID").context("YANDEX_ACCESS_KEY_ID not set")?;
+    let secret_key = env::var("YANDEX_SECRET_ACCESS_KEY_ID").context("YANDEX_SECRET_ACCESS_KEY_ID not set")?;
+    let endpoint = env::var("AWS_ENDPOINT").context("AWS_ENDPOINT not set")?;
+    let region = env::var("AWS_REGION").context("AWS_REGION not set")?;
+
+    Ok(create_client_for(&endpoint, &region, &access_key, &secret_key))
+}
 
+pub fn create_client_for(endpoint: &str, region: &str, access_key: &str, secret_key: &str) -> Client {
     let credentials = Credentials::new(access_key, secret_key, None, None, "custom-provider");
     let config = aws_sdk_s3::Config::builder()
         .credentials_provider(credentials)
-        .region(Region::new(region))
+        .region(Region::new(region.to_string()))
         .endpoint_url(endpoint)
+        .force_path_style(true)
+        // S3-совместимые хранилища не понимают aws-chunked тела с CRC-трейлерами,
+        // поэтому контрольные суммы считаем только там, где этого требует API
+        .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+        .response_checksum_validation(ResponseChecksumValidation::WhenRequired)
+        .retry_config(RetryConfig::standard().with_max_attempts(MAX_ATTEMPTS))
+        .timeout_config(TimeoutConfig::builder().connect_timeout(CONNECT_TIMEOUT).read_timeout(READ_TIMEOUT).build())
         .build();
 
-    let client = Client::from_conf(config);
+    Client::from_conf(config)
+}
+
+pub async fn upload_to_yandex_s3(
+    client: &Client,
+    file_path: &Path,
+    bucket_name: &str,
+    object_key: &str,
+) -> Result<()> {
+    println!("Starting upload to Yandex S3...");
 
     // Проверяем и очищаем незавершенные загрузки
-    cleanup_incomplete_uploads(&client, bucket_name, object_key).await?;
+    cleanup_incomplete_uploads(client, bucket_name, object_key).await?;
 
     let file_size = tokio::fs::metadata(file_path).await?.len();
-    let pb = ProgressBar::new(file_size);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner} {msg} [{wide_bar}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
-            .progress_chars("=> "),
-    );
-    pb.set_message("Uploading to Yandex S3...");
+    let progress = Progress::new("Uploading", Some(file_size));
+    progress.set_file(object_key);
 
     // Определяем минимальный размер для составной загрузки (5MB для Yandex Object Storage)
     const MULTIPART_THRESHOLD: u64 = 5 * 1024 * 1024; // 5MB
 
     if file_size < MULTIPART_THRESHOLD {
         // Простая загрузка для маленьких файлов
-        pb.set_message("Uploading (single part)...");
-
         let body = aws_sdk_s3::primitives::ByteStream::from_path(file_path).await?;
 
         client
@@ -63,11 +83,10 @@ pub async fn upload_to_yandex_s3(
             .send()
             .await?;
 
-        pb.finish_with_message("Upload complete!");
+        progress.inc(file_size);
+        progress.finish("Upload complete!");
     } else {
         // Составная загрузка для больших файлов
-        pb.set_message("Uploading (multipart)...");
-
         let mut file = File::open(file_path).await?;
         let upload_manager = client
             .create_multipart_upload()
@@ -80,11 +99,10 @@ pub async fn upload_to_yandex_s3(
         let mut part_number = 1;
         let mut completed_parts = Vec::new();
         let mut buffer = vec![0; 8 * 1024 * 1024]; // 8MB chunks
-        let start_time = Instant::now();
 
         let upload_result = async {
             loop {
-                let bytes_read = file.read(&mut buffer).await?;
+                let bytes_read = read_part(&mut file, &mut buffer).await?;
                 if bytes_read == 0 {
                     break;
                 }
@@ -101,42 +119,23 @@ pub async fn upload_to_yandex_s3(
                     .await?;
 
                 completed_parts.push(
-                    aws_sdk_s3::types::CompletedPart::builder()
+                    CompletedPart::builder()
                         .part_number(part_number)
                         .e_tag(part_result.e_tag.unwrap_or_default())
                         .build(),
                 );
 
-                pb.inc(bytes_read as u64);
+                progress.inc(bytes_read as u64);
                 part_number += 1;
             }
 
-            Ok::<_, anyhow::Error>(completed_parts)
+            complete_multipart_upload(client, bucket_name, object_key, &upload_id, completed_parts).await
         }.await;
 
         match upload_result {
-            Ok(completed_parts) => {
-                client
-                    .complete_multipart_upload()
-                    .bucket(bucket_name)
-                    .key(object_key)
-                    .upload_id(upload_id)
-                    .multipart_upload(
-                        CompletedMultipartUpload::builder()
-                            .set_parts(Some(completed_parts))
-                            .build(),
-                    )
-                    .send()
-                    .await?;
-
-                let duration = start_time.elapsed();
-                let speed = file_size as f64 / duration.as_secs_f64() / 1024.0 / 1024.0;
-                pb.finish_with_message(format!(
-                    "Upload complete! Speed: {:.2} MB/s",
-                    speed
-                ));
-            }
+            Ok(()) => progress.finish("Upload complete!"),
             Err(e) => {
+                progress.abandon();
                 eprintln!("Upload failed, aborting multipart upload: {:?}", e);
                 client
                     .abort_multipart_upload()
@@ -155,29 +154,16 @@ pub async fn upload_to_yandex_s3(
     Ok(())
 }
 
+/// Downloads `object_key` to `file_path`, resuming interrupted transfers with ranged GETs.
+/// Returns the hex SHA-256 of the downloaded data.
 pub async fn download_from_yandex_s3(
+    client: &Client,
     file_path: &Path,
     bucket_name: &str,
     object_key: &str,
-) -> Result<()> {
-    dotenv().ok();
+) -> Result<String> {
     println!("Starting download from Yandex S3...");
 
-
-    let access_key = env::var("YANDEX_ACCESS_KEY_ID")?;
-    let secret_key = env::var("YANDEX_SECRET_ACCESS_KEY_ID")?;
-    let endpoint = env::var("AWS_ENDPOINT")?;
-    let region = env::var("AWS_REGION")?;
-
-    let credentials = Credentials::new(access_key, secret_key, None, None, "custom-provider");
-    let config = aws_sdk_s3::Config::builder()
-        .credentials_provider(credentials)
-        .region(Region::new(region))
-        .endpoint_url(endpoint)
-        .build();
-
-    let client = Client::from_conf(config);
-
     let head_object = client
         .head_object()
         .bucket(bucket_name)
@@ -186,36 +172,90 @@ pub async fn download_from_yandex_s3(
         .await?;
     let file_size = head_object.content_length.unwrap_or(0) as u64;
 
-    let pb = ProgressBar::new(file_size);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner} {msg} [{wide_bar}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
-            .progress_chars("=> "),
-    );
-    pb.set_message("Downloading from Yandex S3...");
+    let progress = Progress::new("Downloading", Some(file_size));
+    progress.set_file(object_key);
 
     let mut file = File::create(file_path).await?;
-    let mut stream = client
-        .get_object()
-        .bucket(bucket_name)
-        .key(object_key)
-        .send()
-        .await?;
-
-    let start_time = Instant::now();
-    while let Some(bytes) = stream.body.try_next().await? {
-        file.write_all(&bytes).await?;
-        pb.inc(bytes.len() as u64);
+    let mut hasher = Sha256::new();
+    let range = ByteRange { start: 0, len: file_size, whole: true };
+    let result = read_resuming(client, bucket_name, object_key, range, &mut file, |bytes| {
+        hasher.update(bytes);
+        progress.inc(bytes.len() as u64);
+    }, |message| progress.println(message))
+    .await;
+    if let Err(e) = result {
+        progress.abandon();
+        return Err(e);
     }
+    file.flush().await?;
 
-    let duration = start_time.elapsed();
-    let speed = file_size as f64 / duration.as_secs_f64() / 1024.0 / 1024.0;
-    pb.finish_with_message(format!(
-        "Download complete! Speed: {:.2} MB/s",
-        speed
-    ));
+    progress.finish("Download complete!");
 
     println!("File successfully downloaded from Yandex S3");
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Fills `buffer` from `file`; a single `read` on a tokio file returns at most a few MB,
+/// and every part except the last must be at least 5MB.
+async fn read_part(file: &mut File, buffer: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let bytes_read = file.read(&mut buffer[filled..]).await?;
+        if bytes_read == 0 {
+            break;
+        }
+        filled += bytes_read;
+    }
+    Ok(filled)
+}
+
+async fn complete_multipart_upload(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    upload_id: &str,
+    parts: Vec<CompletedPart>,
+) -> Result<()> {
+    let complete = |parts: Vec<CompletedPart>| {
+        client
+            .complete_multipart_upload()
+            .bucket(bucket_name)
+            .key(object_key)
+            .upload_id(upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+            .send()
+    };
+
+    let part_count = parts.len();
+    let error = match complete(parts).await {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+
+    // ETag из ответа UploadPart мог прийти искажённым - сверяемся со списком частей в самом хранилище
+    let mut stored_parts = Vec::new();
+    let mut pages = client
+        .list_parts()
+        .bucket(bucket_name)
+        .key(object_key)
+        .upload_id(upload_id)
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        for part in page?.parts() {
+            stored_parts.push(
+                CompletedPart::builder()
+                    .set_part_number(part.part_number())
+                    .set_e_tag(part.e_tag().map(|t| t.to_string()))
+                    .build(),
+            );
+        }
+    }
+
+    if stored_parts.len() != part_count {
+        return Err(anyhow::Error::new(error).context("Failed to complete multipart upload"));
+    }
+    complete(stored_parts).await.context("Failed to complete multipart upload")?;
     Ok(())
 }
 
@@ -246,3 +286,177 @@ async fn cleanup_incomplete_uploads(
     }
     Ok(())
 }
+
+#[derive(Debug, PartialEq)]
+pub enum BucketStatus {
+    Available,
+    Missing,
+    AccessDenied,
+}
+
+pub async fn check_bucket(client: &Client, bucket_name: &str) -> Result<BucketStatus> {
+    match client.head_bucket().bucket(bucket_name).send().await {
+        Ok(_) => Ok(BucketStatus::Available),
+        Err(e) => match e.raw_response().map(|r| r.status().as_u16()) {
+            Some(404) => Ok(BucketStatus::Missing),
+            Some(401 | 403) => Ok(BucketStatus::AccessDenied),
+            _ => Err(anyhow::Error::new(e).context(format!("Failed to reach bucket '{}'", bucket_name))),
+        },
+    }
+}
+
+pub async fn create_bucket(client: &Client, bucket_name: &str) -> Result<()> {
+    client
+        .create_bucket()
+        .bucket(bucket_name)
+        .send()
+        .await
+        .with_context(|| format!("Failed to create bucket '{}'", bucket_name))?;
+    Ok(())
+}
+
+pub async fn object_exists(client: &Client, bucket_name: &str, object_key: &str) -> Result<bool> {
+    match client.head_object().bucket(bucket_name).key(object_key).send().await {
+        Ok(_) => Ok(true),
+        Err(e) if e.raw_response().map(|r| r.status().as_u16()) == Some(404) => Ok(false),
+        Err(e) => Err(anyhow::Error::new(e).context(format!("Failed to check object '{}'", object_key))),
+    }
+}
+
+pub async fn put_bytes(client: &Client, bucket_name: &str, object_key: &str, data: Vec<u8>) -> Result<()> {
+    client
+        .put_object()
+        .bucket(bucket_name)
+        .key(object_key)
+        .body(data.into())
+        .send()
+        .await
+        .with_context(|| format!("Failed to upload '{}'", object_key))?;
+    Ok(())
+}
+
+/// Uploads `data` only if `object_key` doesn't exist yet (`If-None-Match: *`).
+/// Returns `false` when the object already exists and nothing was written.
+pub async fn put_bytes_if_absent(client: &Client, bucket_name: &str, object_key: &str, data: Vec<u8>) -> Result<bool> {
+    let result = client
+        .put_object()
+        .bucket(bucket_name)
+        .key(object_key)
+        .if_none_match("*")
+        .body(data.into())
+        .send()
+        .await;
+    match result {
+        Ok(_) => Ok(true),
+        // 409 - конкурирующая условная запись того же ключа ещё не завершилась
+        Err(e) if matches!(e.raw_response().map(|r| r.status().as_u16()), Some(412 | 409)) => Ok(false),
+        Err(e) => Err(anyhow::Error::new(e).context(format!("Failed to upload '{}'", object_key))),
+    }
+}
+
+pub async fn get_bytes(client: &Client, bucket_name: &str, object_key: &str) -> Result<Vec<u8>> {
+    let object = client
+        .get_object()
+        .bucket(bucket_name)
+        .key(object_key)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download '{}'", object_key))?;
+    let data = object.body.collect().await?;
+    Ok(data.into_bytes().to_vec())
+}
+
+/// Fetches `len` bytes of `object_key` starting at `start` with a ranged GET, resuming an
+/// interrupted response like [`download_from_yandex_s3`]. `on_retry` gets the message of each
+/// resumption, e.g. to print it above the progress bar of the caller.
+pub async fn get_range(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    start: u64,
+    len: u64,
+    on_retry: impl Fn(String),
+) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(len as usize);
+    let range = ByteRange { start, len, whole: false };
+    read_resuming(client, bucket_name, object_key, range, &mut data, |_| {}, on_retry)
+        .await
+        .with_context(|| format!("Failed to read bytes {}..{} of '{}'", start, start + len, object_key))?;
+    Ok(data)
+}
+
+/// Bytes `start..start + len` of an object; `whole` when that is the entire object, which is then
+/// first requested without a range.
+struct ByteRange {
+    start: u64,
+    len: u64,
+    whole: bool,
+}
+
+/// Writes `range` of `object_key` to `sink`. The SDK retries failed requests but not a response
+/// body that breaks off, so an interrupted or short body is resumed with a ranged GET from where
+/// it stopped, up to [`MAX_ATTEMPTS`] times. `on_data` sees every piece written, `on_retry` a
+/// message for every resumption.
+async fn read_resuming<W: AsyncWrite + Unpin>(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    range: ByteRange,
+    sink: &mut W,
+    mut on_data: impl FnMut(&[u8]),
+    on_retry: impl Fn(String),
+) -> Result<()> {
+    let ByteRange { start, len, whole } = range;
+    let mut received: u64 = 0;
+    let mut failures = 0;
+
+    loop {
+        let mut request = client.get_object().bucket(bucket_name).key(object_key);
+        if received > 0 || !whole {
+            request = request.range(format!("bytes={}-{}", start + received, start + len - 1));
+        }
+
+        let result = async {
+            let mut stream = request.send().await?;
+            while let Some(bytes) = stream.body.try_next().await? {
+                sink.write_all(&bytes).await?;
+                on_data(&bytes);
+                received += bytes.len() as u64;
+            }
+            Ok::<_, anyhow::Error>(())
+        }.await;
+
+        let error = match result {
+            Ok(()) if received == len => return Ok(()),
+            Ok(()) if received > len => bail!(
+                "Received {} bytes for '{}', expected {}", received, object_key, len
+            ),
+            Ok(()) => anyhow!("connection closed after {} of {} bytes", received, len),
+            Err(e) => e,
+        };
+
+        // Тело ответа S3 SDK не перезапрашивает сам, поэтому докачиваем с места обрыва
+        failures += 1;
+        if failures >= MAX_ATTEMPTS {
+            return Err(error.context(format!("Download of '{}' failed after {} attempts", object_key, failures)));
+        }
+        on_retry(format!("Download interrupted ({:#}), resuming from byte {}...", error, start + received));
+        tokio::time::sleep(Duration::from_millis(200 * failures as u64)).await;
+    }
+}
+
+pub async fn list_keys(client: &Client, bucket_name: &str, prefix: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut pages = client
+        .list_objects_v2()
+        .bucket(bucket_name)
+        .prefix(prefix)
+        .into_paginator()
+        .send();
+
+    while let Some(page) = pages.next().await {
+        let page = page.with_context(|| format!("Failed to list objects under '{}'", prefix))?;
+        keys.extend(page.contents().iter().filter_map(|o| o.key().map(|k| k.to_string())));
+    }
+    Ok(keys)
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let (digest, _) = sha256_volumes(&[path.to_path_buf()])?;
+    Ok(digest)
+}
+
+/// Hex SHA-256 of everything `reader` yields.
+pub fn sha256_reader(reader: &mut dyn Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hashes `paths` as one concatenated stream in a single pass.
+/// Returns the digest of the whole stream and the digest of every part.
+pub fn sha256_volumes(paths: &[PathBuf]) -> anyhow::Result<(String, Vec<String>)> {
+    let mut hasher = Sha256::new();
+    let mut volume_digests = Vec::with_capacity(paths.len());
+    let mut buffer = vec![0; 1024 * 1024];
+
+    for path in paths {
+        let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut volume_hasher = Sha256::new();
+        loop {
+            let bytes_read = file.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
+            volume_hasher.update(&buffer[..bytes_read]);
+        }
+        volume_digests.push(hex::encode(volume_hasher.finalize()));
+    }
+
+    Ok((hex::encode(hasher.finalize()), volume_digests))
+}
+
+/// Digest of a downloaded object that differs from the one recorded when it was uploaded.
+#[derive(Debug, Clone)]
+pub struct ChecksumMismatch {
+    pub key: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Checksum mismatch for {}: expected {}, got {}", self.key, self.expected, self.actual)
+    }
+}
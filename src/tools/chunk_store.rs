@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{bail, Context};
+use aws_sdk_s3::Client;
+use fastcdc::v2020::StreamCDC;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::runtime::Handle;
+use crate::tools;
+use crate::tools::progress::Progress;
+use crate::utils::backup_meta::ChunkRef;
+use crate::utils::local_state::PackIndexCache;
+
+/// Bucket-wide prefix of the chunk store, shared by every project in the bucket.
+pub const STORE_PREFIX: &str = "chunks/";
+/// Content-defined chunk bounds. Cut points depend on the bytes around them, so an edit only
+/// changes the chunks it touches and everything after them lines up with the previous version.
+const MIN_CHUNK_SIZE: u32 = 64 * 1024;
+const AVG_CHUNK_SIZE: u32 = 256 * 1024;
+const MAX_CHUNK_SIZE: u32 = 1024 * 1024;
+/// A pack is closed and a new one started once it reaches this size.
+const PACK_SIZE: u64 = 32 * 1024 * 1024;
+const ZSTD_LEVEL: i32 = 3;
+/// Longest run of adjacent chunks fetched with one GET on restore.
+const MAX_RUN: u64 = 16 * 1024 * 1024;
+
+/// Where `push` keeps file contents.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Storage {
+    /// An archive per backup, holding the files changed since the previous one.
+    #[default]
+    Archive,
+    /// Deduplicated chunks in the bucket's chunk store, see [`STORE_PREFIX`].
+    Chunks,
+}
+
+impl Storage {
+    pub const ALL: [Storage; 2] = [Storage::Archive, Storage::Chunks];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Storage::Archive => "archive",
+            Storage::Chunks => "chunks",
+        }
+    }
+}
+
+impl fmt::Display for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Storage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match Storage::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(s)) {
+            Some(storage) => Ok(storage),
+            None => {
+                let known: Vec<_> = Storage::ALL.iter().map(|p| p.name()).collect();
+                bail!("Unknown storage '{}', expected one of: {}", s, known.join(", "))
+            }
+        }
+    }
+}
+
+/// What a push to the chunk store did, shown in the push summary.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkReport {
+    /// Files read and chunked; the others were unchanged since the previous backup.
+    pub chunked_files: usize,
+    pub chunked_bytes: u64,
+    /// Chunks of the chunked files, and how many of them the store didn't have.
+    pub chunks: usize,
+    pub new_chunks: usize,
+    /// Uncompressed size of the new chunks.
+    pub new_bytes: u64,
+    pub packs: usize,
+}
+
+/// Chunks of one pack object, stored next to it as `chunks/index/<pack>.json`. A push reads the
+/// indexes it hasn't cached yet to learn which chunks the bucket already has.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PackIndex {
+    pub chunks: Vec<ChunkRef>,
+}
+
+pub fn pack_key(pack: &str) -> String {
+    format!("{}packs/{}.pack", STORE_PREFIX, pack)
+}
+
+pub fn index_key(pack: &str) -> String {
+    format!("{}index/{}.json", STORE_PREFIX, pack)
+}
+
+/// Every chunk in the bucket's chunk store by its id. Only the indexes missing from the
+/// [`PackIndexCache`] of `project_dir` are downloaded; cached packs no longer listed are dropped.
+pub async fn load_index(client: &Client, bucket: &str, project_dir: &Path) -> anyhow::Result<HashMap<String, ChunkRef>> {
+    let prefix = format!("{}index/", STORE_PREFIX);
+    let keys = tools::aws::list_keys(client, bucket, &prefix).await?;
+    let listed: HashSet<&str> = keys
+        .iter()
+        .filter_map(|k| k.strip_prefix(prefix.as_str())?.strip_suffix(".json"))
+        .collect();
+
+    let mut cache = PackIndexCache::load(project_dir, bucket);
+    cache.packs.retain(|pack, _| listed.contains(pack.as_str()));
+    for pack in listed.into_iter().filter(|pack| !cache.packs.contains_key(*pack)).collect::<Vec<_>>() {
+        let key = index_key(pack);
+        let data = tools::aws::get_bytes(client, bucket, &key).await?;
+        let index: PackIndex = serde_json::from_slice(&data).with_context(|| format!("Failed to parse pack index '{}'", key))?;
+        cache.packs.insert(pack.to_string(), index.chunks);
+    }
+    cache.save(project_dir)?;
+
+    Ok(cache.packs.into_values().flatten().map(|chunk| (chunk.id.clone(), chunk)).collect())
+}
+
+/// Pack written by [`PackWriter`], waiting to be uploaded together with its index.
+pub struct NewPack {
+    pub id: String,
+    pub path: PathBuf,
+    pub index: PackIndex,
+}
+
+struct OpenPack {
+    id: String,
+    path: PathBuf,
+    file: BufWriter<fs::File>,
+    chunks: Vec<ChunkRef>,
+    size: u64,
+}
+
+/// Splits files into chunks and writes the ones the store doesn't have yet into pack files,
+/// each chunk compressed on its own so it can be fetched with a ranged GET.
+pub struct PackWriter {
+    dir: PathBuf,
+    known: HashMap<String, ChunkRef>,
+    current: Option<OpenPack>,
+    packs: Vec<NewPack>,
+    report: ChunkReport,
+}
+
+impl PackWriter {
+    /// Packs are written to `dir`; `known` is the store's index, see [`load_index`].
+    pub fn new(dir: &Path, known: HashMap<String, ChunkRef>) -> Self {
+        PackWriter {
+            dir: dir.to_path_buf(),
+            known,
+            current: None,
+            packs: Vec::new(),
+            report: ChunkReport::default(),
+        }
+    }
+
+    /// Chunks the contents of `reader`, packing the chunks not stored yet. Returns the chunks in
+    /// order and the hex SHA-256 of the whole contents.
+    pub fn add_file(&mut self, reader: &mut dyn Read) -> anyhow::Result<(Vec<ChunkRef>, String)> {
+        let mut hasher = Sha256::new();
+        let mut chunks = Vec::new();
+        for chunk in StreamCDC::new(reader, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE) {
+            let chunk = chunk.map_err(io::Error::from)?;
+            hasher.update(&chunk.data);
+            let id = hex::encode(Sha256::digest(&chunk.data));
+            let chunk_ref = match self.known.get(&id) {
+                Some(stored) => stored.clone(),
+                None => {
+                    let stored = self.store(&id, &chunk.data)?;
+                    self.known.insert(id, stored.clone());
+                    stored
+                }
+            };
+            chunks.push(chunk_ref);
+        }
+        self.report.chunked_files += 1;
+        self.report.chunked_bytes += chunks.iter().map(|c| c.size).sum::<u64>();
+        self.report.chunks += chunks.len();
+        Ok((chunks, hex::encode(hasher.finalize())))
+    }
+
+    /// Closes the last pack and returns every pack written.
+    pub fn finish(mut self) -> anyhow::Result<(Vec<NewPack>, ChunkReport)> {
+        self.close()?;
+        self.report.packs = self.packs.len();
+        Ok((self.packs, self.report))
+    }
+
+    fn store(&mut self, id: &str, data: &[u8]) -> anyhow::Result<ChunkRef> {
+        if self.current.is_none() {
+            let id = new_pack_id(id, self.packs.len());
+            let path = self.dir.join(format!("{}.pack", id));
+            let file = fs::File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+            self.current = Some(OpenPack { id, path, file: BufWriter::new(file), chunks: Vec::new(), size: 0 });
+        }
+        let pack = self.current.as_mut().unwrap();
+
+        let compressed = zstd::bulk::compress(data, ZSTD_LEVEL)?;
+        pack.file.write_all(&compressed)?;
+        let chunk = ChunkRef {
+            id: id.to_string(),
+            pack: pack.id.clone(),
+            offset: pack.size,
+            length: compressed.len() as u64,
+            size: data.len() as u64,
+        };
+        pack.size += chunk.length;
+        pack.chunks.push(chunk.clone());
+        let full = pack.size >= PACK_SIZE;
+        self.report.new_chunks += 1;
+        self.report.new_bytes += chunk.size;
+
+        if full {
+            self.close()?;
+        }
+        Ok(chunk)
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        if let Some(mut pack) = self.current.take() {
+            pack.file.flush()?;
+            self.packs.push(NewPack { id: pack.id, path: pack.path, index: PackIndex { chunks: pack.chunks } });
+        }
+        Ok(())
+    }
+}
+
+/// Packs are named before their contents are known, so the id mixes the first chunk with the
+/// time and process: two pushes starting a pack with the same new chunk still get different keys.
+fn new_pack_id(first_chunk: &str, sequence: usize) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let seed = format!("{}:{}:{}:{}", first_chunk, nanos, std::process::id(), sequence);
+    hex::encode(Sha256::digest(seed.as_bytes()))
+}
+
+/// Uploads `packs`, each followed by its index, so an index never lists a chunk whose pack is
+/// missing, and adds them to the [`PackIndexCache`] of `project_dir`. Returns the bytes uploaded.
+pub async fn upload_packs(client: &Client, bucket: &str, packs: Vec<NewPack>, project_dir: &Path) -> anyhow::Result<u64> {
+    let mut bytes = 0;
+    let mut cache = PackIndexCache::load(project_dir, bucket);
+    for (index, pack) in packs.iter().enumerate() {
+        println!("Uploading pack {}/{}", index + 1, packs.len());
+        bytes += fs::metadata(&pack.path)?.len();
+        tools::aws::upload_to_yandex_s3(client, &pack.path, bucket, &pack_key(&pack.id)).await?;
+        tools::aws::put_bytes(client, bucket, &index_key(&pack.id), serde_json::to_vec(&pack.index)?).await?;
+        fs::remove_file(&pack.path).context("Failed to remove temporary pack file")?;
+        cache.packs.insert(pack.id.clone(), pack.index.chunks.clone());
+    }
+    if !packs.is_empty() {
+        cache.save(project_dir)?;
+    }
+    Ok(bytes)
+}
+
+/// Contents of a file rebuilt from its chunks, fetching runs of chunks that are adjacent in a
+/// pack with one ranged GET and checking every chunk against its hash. Reads block on the
+/// runtime the reader was created on, so it must be used from a blocking thread.
+pub struct ChunkReader<'a> {
+    client: &'a Client,
+    bucket: &'a str,
+    chunks: &'a [ChunkRef],
+    /// Bar of the restore or verification, retries are printed above it.
+    progress: &'a Progress,
+    next: usize,
+    buffer: Vec<u8>,
+    position: usize,
+    fetched: u64,
+    runtime: Handle,
+}
+
+impl<'a> ChunkReader<'a> {
+    pub fn new(client: &'a Client, bucket: &'a str, chunks: &'a [ChunkRef], progress: &'a Progress) -> Self {
+        ChunkReader {
+            client,
+            bucket,
+            chunks,
+            progress,
+            next: 0,
+            buffer: Vec::new(),
+            position: 0,
+            fetched: 0,
+            runtime: Handle::current(),
+        }
+    }
+
+    /// Bytes downloaded so far.
+    pub fn fetched(&self) -> u64 {
+        self.fetched
+    }
+
+    /// Decodes the next run of chunks into the buffer; `false` at the end of the file.
+    fn fill(&mut self) -> io::Result<bool> {
+        let Some(first) = self.chunks.get(self.next) else {
+            return Ok(false);
+        };
+        let mut end = self.next + 1;
+        let mut length = first.length;
+        while let Some(chunk) = self.chunks.get(end) {
+            let previous = &self.chunks[end - 1];
+            if chunk.pack != first.pack || chunk.offset != previous.offset + previous.length || length + chunk.length > MAX_RUN {
+                break;
+            }
+            length += chunk.length;
+            end += 1;
+        }
+
+        let key = pack_key(&first.pack);
+        let progress = self.progress;
+        let range = tools::aws::get_range(self.client, self.bucket, &key, first.offset, length, |message| progress.println(message));
+        let data = self.runtime.block_on(range).map_err(io::Error::other)?;
+        self.fetched += data.len() as u64;
+        if data.len() as u64 != length {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("Pack {} is truncated", first.pack)));
+        }
+
+        self.buffer.clear();
+        self.position = 0;
+        for chunk in &self.chunks[self.next..end] {
+            let start = (chunk.offset - first.offset) as usize;
+            let corrupt = || io::Error::new(io::ErrorKind::InvalidData, format!("Chunk {} in pack {} is corrupt", chunk.id, chunk.pack));
+            let plain = zstd::bulk::decompress(&data[start..start + chunk.length as usize], chunk.size as usize).map_err(|_| corrupt())?;
+            if hex::encode(Sha256::digest(&plain)) != chunk.id {
+                return Err(corrupt());
+            }
+            self.buffer.extend_from_slice(&plain);
+        }
+        self.next = end;
+        Ok(true)
+    }
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.buffer.len() - self.position);
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Attributes kept with every archive entry and reapplied on extraction, so a restored project
+/// keeps its timestamps (Unreal rebuilds and re-cooks anything that looks newer) and permissions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryMetadata {
+    pub modified: Option<SystemTime>,
+    /// Unix permission bits. Windows has only a read-only flag: it is captured as a mode without
+    /// write bits, and only the write bits are applied there.
+    pub mode: Option<u32>,
+}
+
+impl EntryMetadata {
+    pub fn from_fs(metadata: &fs::Metadata) -> Self {
+        EntryMetadata {
+            modified: metadata.modified().ok(),
+            mode: Some(mode_of(metadata)),
+        }
+    }
+
+    /// Applies `mode` to the file or directory at `path`; the modification time is set while
+    /// the file is still open for writing.
+    pub fn apply_permissions(&self, path: &Path) -> io::Result<()> {
+        match self.mode {
+            Some(mode) => set_mode(path, mode),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn mode_of(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode_of(metadata: &fs::Metadata) -> u32 {
+    let mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+    if metadata.permissions().readonly() { mode & !0o222 } else { mode }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o222 == 0);
+    fs::set_permissions(path, permissions)
+}
+
+/// Clears the read-only flag of an existing file so it can be replaced.
+pub fn make_writable(path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.file_type().is_file() && metadata.permissions().readonly() {
+        set_mode(path, mode_of(&metadata) | 0o200)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{bail, Context};
+use chrono::Utc;
+use super::attributes::make_writable;
+use super::{EntryMetadata, ExtractReport, OverwritePolicy};
+use crate::tools::progress::Progress;
+
+/// Directory under the project root that [`OverwritePolicy::Trash`] moves replaced files to,
+/// one subdirectory per extraction.
+pub const TRASH_DIR: &str = ".rsget/trash";
+
+/// Writes archive entries under a root directory. Every format extracts through it, so path
+/// checks and the overwrite policy don't depend on what the archive library does.
+pub struct EntryWriter<'a> {
+    root: &'a Path,
+    policy: OverwritePolicy,
+    progress: &'a Progress,
+    trash_dir: PathBuf,
+    /// Link entries as `(name, target)`, created by [`EntryWriter::finish`].
+    links: Vec<(String, String)>,
+    report: ExtractReport,
+}
+
+impl<'a> EntryWriter<'a> {
+    pub fn new(root: &'a Path, policy: OverwritePolicy, progress: &'a Progress) -> Self {
+        let trash_dir = root.join(TRASH_DIR).join(Utc::now().format("%Y%m%dT%H%M%SZ").to_string());
+        EntryWriter {
+            root,
+            policy,
+            progress,
+            trash_dir,
+            links: Vec::new(),
+            report: ExtractReport::default(),
+        }
+    }
+
+    pub fn create_dir(&mut self, name: &str) -> anyhow::Result<()> {
+        let relative_path = safe_entry_path(name)?;
+        if let Some(link) = self.linked_ancestor(&relative_path, true) {
+            self.warn(format!("{} not created: {} is a link", name, link.display()));
+            return Ok(());
+        }
+        let path = self.root.join(relative_path);
+        fs::create_dir_all(&path).with_context(|| format!("Failed to create {}", path.display()))
+    }
+
+    /// Writes the entry `name` with the contents of `reader` and applies its `metadata`. The entry's
+    /// mtime is also what [`OverwritePolicy::Newer`] compares; without it the local file is replaced.
+    /// Skipped entries are still read to the end, since some formats decode entries from one stream.
+    pub fn write_file(&mut self, name: &str, metadata: &EntryMetadata, reader: &mut dyn Read) -> anyhow::Result<()> {
+        let relative_path = safe_entry_path(name)?;
+        let reader = &mut self.progress.reader(name, reader);
+        if let Some(link) = self.linked_ancestor(&relative_path, false) {
+            self.warn(format!("{} not written: {} is a link", name, link.display()));
+            return self.keep(reader);
+        }
+        let Some(path) = self.destination(&relative_path, metadata.modified)? else {
+            return self.keep(reader);
+        };
+
+        // Never write through a link left in place of the file
+        match fs::symlink_metadata(&path) {
+            Ok(existing) if existing.file_type().is_symlink() => fs::remove_file(&path)?,
+            Ok(_) => make_writable(&path).with_context(|| format!("Failed to make {} writable", path.display()))?,
+            Err(_) => {}
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = fs::File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        io::copy(reader, &mut file).with_context(|| format!("Failed to extract {}", name))?;
+        if let Some(modified) = metadata.modified {
+            file.set_modified(modified).with_context(|| format!("Failed to set the modification time of {}", path.display()))?;
+        }
+        drop(file);
+        metadata
+            .apply_permissions(&path)
+            .with_context(|| format!("Failed to set the permissions of {}", path.display()))?;
+        self.report.extracted += 1;
+        Ok(())
+    }
+
+    /// Records the link entry `name` pointing at `target`. Links are only created by
+    /// [`EntryWriter::finish`], after every other entry, so no entry is ever written through a
+    /// link that came from the archive.
+    pub fn add_link(&mut self, name: &str, target: &str) -> anyhow::Result<()> {
+        safe_entry_path(name)?;
+        self.links.push((name.to_string(), target.to_string()));
+        Ok(())
+    }
+
+    /// Creates the recorded links and returns the report. A link the platform refuses to create,
+    /// e.g. on Windows without the symlink privilege, is a warning rather than an error.
+    pub fn finish(mut self) -> anyhow::Result<ExtractReport> {
+        for (name, target) in std::mem::take(&mut self.links) {
+            self.create_link(&name, &target)?;
+        }
+        if self.report.trashed > 0 {
+            self.report.trash_dir = Some(self.trash_dir);
+        }
+        Ok(self.report)
+    }
+
+    fn create_link(&mut self, name: &str, target: &str) -> anyhow::Result<()> {
+        let relative_path = safe_entry_path(name)?;
+        if link_escapes(&relative_path, target) {
+            self.warn(format!("Link {} -> {} not created: it points outside the project", name, target));
+            return Ok(());
+        }
+        if let Some(link) = self.linked_ancestor(&relative_path, false) {
+            self.warn(format!("Link {} -> {} not created: {} is a link", name, target, link.display()));
+            return Ok(());
+        }
+        let Some(path) = self.destination(&relative_path, None)? else {
+            self.report.kept += 1;
+            return Ok(());
+        };
+
+        match fs::symlink_metadata(&path) {
+            Ok(existing) if existing.is_dir() => {
+                self.warn(format!("Link {} -> {} not created: a directory is in its place", name, target));
+                return Ok(());
+            }
+            Ok(_) => {
+                make_writable(&path).with_context(|| format!("Failed to make {} writable", path.display()))?;
+                fs::remove_file(&path).with_context(|| format!("Failed to replace {}", path.display()))?;
+            }
+            Err(_) => {}
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        match symlink(target, &path) {
+            Ok(()) => self.report.linked += 1,
+            Err(e) => self.warn(format!("Link {} -> {} not created: {}", name, target, e)),
+        }
+        Ok(())
+    }
+
+    /// Applies the overwrite policy to the entry at `relative_path`: `None` when the local file
+    /// is kept, otherwise the path the entry goes to.
+    fn destination(&mut self, relative_path: &Path, modified: Option<SystemTime>) -> anyhow::Result<Option<PathBuf>> {
+        let mut path = self.root.join(relative_path);
+        if fs::symlink_metadata(&path).is_ok() {
+            match self.policy {
+                OverwritePolicy::Overwrite => {}
+                OverwritePolicy::Skip => return Ok(None),
+                OverwritePolicy::Newer if is_newer(&path, modified)? => return Ok(None),
+                OverwritePolicy::Newer => {}
+                OverwritePolicy::Rename => {
+                    path = free_name(&path);
+                    self.report.renamed += 1;
+                }
+                OverwritePolicy::Trash => {
+                    let trashed = self.trash_dir.join(relative_path);
+                    if let Some(parent) = trashed.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::rename(&path, &trashed).with_context(|| format!("Failed to move {} to the trash", path.display()))?;
+                    self.report.trashed += 1;
+                }
+            }
+        }
+        Ok(Some(path))
+    }
+
+    /// The first directory of `relative_path` that is a link under the root, including the path
+    /// itself when `itself` is set. Nothing is written through one, it may lead out of the root.
+    fn linked_ancestor(&self, relative_path: &Path, itself: bool) -> Option<PathBuf> {
+        let mut path = PathBuf::new();
+        let mut components = relative_path.components().peekable();
+        while let Some(component) = components.next() {
+            if components.peek().is_none() && !itself {
+                break;
+            }
+            path.push(component);
+            if fs::symlink_metadata(self.root.join(&path)).is_ok_and(|m| m.file_type().is_symlink()) {
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    fn warn(&mut self, warning: String) {
+        self.progress.println(format!("Warning: {}", warning));
+        self.report.link_warnings.push(warning);
+    }
+
+    fn keep(&mut self, reader: &mut dyn Read) -> anyhow::Result<()> {
+        io::copy(reader, &mut io::sink())?;
+        self.report.kept += 1;
+        Ok(())
+    }
+}
+
+/// Turns an archive entry name into a path relative to the extraction root. Both `/` and `\`
+/// are separators, and absolute names, drive prefixes and `..` components are rejected,
+/// so no entry can land outside the root.
+pub fn safe_entry_path(name: &str) -> anyhow::Result<PathBuf> {
+    let unsafe_path = || anyhow::anyhow!("Unsafe path in archive: '{}'", name);
+    if name.starts_with(['/', '\\']) {
+        return Err(unsafe_path());
+    }
+
+    let mut path = PathBuf::new();
+    for (index, part) in name.split(['/', '\\']).enumerate() {
+        match part {
+            "" | "." => {}
+            ".." => return Err(unsafe_path()),
+            _ if index == 0 && part.len() >= 2 && part.as_bytes()[1] == b':' => return Err(unsafe_path()),
+            _ => path.push(part),
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        bail!("Empty path in archive: '{}'", name);
+    }
+    Ok(path)
+}
+
+/// Whether the link at `relative_path` pointing at `target` leads outside the root: an absolute
+/// target, or one whose `..` climb above the root. A `..` after a name is refused too, since that
+/// name may itself be a link.
+fn link_escapes(relative_path: &Path, target: &str) -> bool {
+    if target.starts_with(['/', '\\']) || (target.len() >= 2 && target.as_bytes()[1] == b':') {
+        return true;
+    }
+    let mut depth = relative_path.components().count() - 1;
+    let mut descended = false;
+    for part in target.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." if descended || depth == 0 => return true,
+            ".." => depth -= 1,
+            _ => descended = true,
+        }
+    }
+    false
+}
+
+fn is_newer(path: &Path, entry_modified: Option<SystemTime>) -> anyhow::Result<bool> {
+    let Some(entry_modified) = entry_modified else {
+        return Ok(false);
+    };
+    let modified = fs::metadata(path)?.modified()?;
+    Ok(modified > entry_modified)
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+/// Windows tells file and directory links apart, so the kind is taken from what the target is
+/// now; a dangling target becomes a file link. Junctions come back as directory links.
+#[cfg(windows)]
+fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    let target = target.replace('/', "\\");
+    let resolved = path.parent().unwrap_or(Path::new("")).join(&target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(&target, path)
+    } else {
+        std::os::windows::fs::symlink_file(&target, path)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_target: &str, _path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "links are not supported on this platform"))
+}
+
+/// `Main.umap` -> `Main (1).umap`, or the first of `(2)`, `(3)`, ... that doesn't exist yet.
+fn free_name(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .expect("some numbered name is free")
+}
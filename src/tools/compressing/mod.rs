@@ -0,0 +1,825 @@
+mod attributes;
+mod extract;
+mod seven_z;
+mod tar_zstd;
+mod volumes;
+mod zip_archive;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use anyhow::bail;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use crate::tools::ignore_rules::{IgnoreRules, PathSelection};
+use crate::tools::path_names::PortableNames;
+use crate::tools::progress::Progress;
+use crate::utils::project_config::BackupProfile;
+
+pub use attributes::EntryMetadata;
+pub use extract::{safe_entry_path, EntryWriter, TRASH_DIR};
+pub use seven_z::SevenZCompressor;
+pub use tar_zstd::TarZstdCompressor;
+pub use volumes::VolumeWriter;
+pub use zip_archive::ZipCompressor;
+
+pub struct ProjectFile {
+    pub path: PathBuf,
+    /// Path relative to the project root with `/` separators, used as the archive entry name.
+    pub relative_path: String,
+    pub size: u64,
+}
+
+/// Empty directory, stored as its own entry so it survives a restore.
+#[derive(Debug, Clone)]
+pub struct ProjectDir {
+    pub path: PathBuf,
+    /// See [`ProjectFile::relative_path`].
+    pub relative_path: String,
+}
+
+/// Symbolic link (or Windows junction) stored as a link under [`SymlinkPolicy::Store`].
+#[derive(Debug, Clone)]
+pub struct ProjectLink {
+    pub path: PathBuf,
+    /// See [`ProjectFile::relative_path`].
+    pub relative_path: String,
+    /// Link target as read from the link, with `/` separators.
+    pub target: String,
+}
+
+/// Everything that goes into a backup, see [`collect_project`].
+pub struct ProjectContents {
+    pub files: Vec<ProjectFile>,
+    pub empty_dirs: Vec<ProjectDir>,
+    pub links: Vec<ProjectLink>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "7z")]
+    SevenZ,
+    #[serde(rename = "tar.zst")]
+    TarZstd,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 3] = [ArchiveFormat::SevenZ, ArchiveFormat::TarZstd, ArchiveFormat::Zip];
+
+    /// File extension without the leading dot, also used for object keys.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::SevenZ => "7z",
+            ArchiveFormat::TarZstd => "tar.zst",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    pub fn compressor(self) -> Box<dyn Compressor> {
+        match self {
+            ArchiveFormat::SevenZ => Box::new(SevenZCompressor),
+            ArchiveFormat::TarZstd => Box::new(TarZstdCompressor),
+            ArchiveFormat::Zip => Box::new(ZipCompressor),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "7z" => Ok(ArchiveFormat::SevenZ),
+            "tar.zst" | "tzst" | "zstd" => Ok(ArchiveFormat::TarZstd),
+            "zip" => Ok(ArchiveFormat::Zip),
+            _ => {
+                let known: Vec<_> = ArchiveFormat::ALL.iter().map(|f| f.extension()).collect();
+                bail!("Unknown archive format '{}', expected one of: {}", s, known.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionMethod {
+    Lzma2,
+    Lzma,
+    Deflate,
+    Bzip2,
+    Zstd,
+    Store,
+}
+
+impl CompressionMethod {
+    pub const ALL: [CompressionMethod; 6] = [
+        CompressionMethod::Lzma2,
+        CompressionMethod::Lzma,
+        CompressionMethod::Deflate,
+        CompressionMethod::Bzip2,
+        CompressionMethod::Zstd,
+        CompressionMethod::Store,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CompressionMethod::Lzma2 => "lzma2",
+            CompressionMethod::Lzma => "lzma",
+            CompressionMethod::Deflate => "deflate",
+            CompressionMethod::Bzip2 => "bzip2",
+            CompressionMethod::Zstd => "zstd",
+            CompressionMethod::Store => "store",
+        }
+    }
+
+    pub fn levels(self) -> RangeInclusive<u32> {
+        match self {
+            CompressionMethod::Lzma2 | CompressionMethod::Lzma | CompressionMethod::Deflate => 0..=9,
+            CompressionMethod::Bzip2 => 1..=9,
+            CompressionMethod::Zstd => 1..=22,
+            CompressionMethod::Store => 0..=0,
+        }
+    }
+
+    pub fn default_level(self) -> u32 {
+        match self {
+            CompressionMethod::Lzma2 | CompressionMethod::Lzma | CompressionMethod::Deflate | CompressionMethod::Bzip2 => 6,
+            CompressionMethod::Zstd => 3,
+            CompressionMethod::Store => 0,
+        }
+    }
+}
+
+impl fmt::Display for CompressionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CompressionMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match CompressionMethod::ALL.into_iter().find(|m| m.name().eq_ignore_ascii_case(s)) {
+            Some(method) => Ok(method),
+            None => {
+                let known: Vec<_> = CompressionMethod::ALL.iter().map(|m| m.name()).collect();
+                bail!("Unknown compression method '{}', expected one of: {}", s, known.join(", "))
+            }
+        }
+    }
+}
+
+/// Compression settings from the project config; unset fields fall back to the format defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompressionSettings {
+    #[serde(default)]
+    pub method: Option<CompressionMethod>,
+    #[serde(default)]
+    pub level: Option<u32>,
+    /// Compression threads; `None` uses every available core.
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// Extensions (without the dot, case-insensitive) of already-compressed files that are stored as is.
+    #[serde(default = "default_store_extensions")]
+    pub store_extensions: Vec<String>,
+    /// Encrypts the archive with AES-256. Never read from or written to the config file.
+    #[serde(skip)]
+    pub password: Option<String>,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        CompressionSettings {
+            method: None,
+            level: None,
+            threads: None,
+            store_extensions: default_store_extensions(),
+            password: None,
+        }
+    }
+}
+
+fn default_store_extensions() -> Vec<String> {
+    ["mp4", "mov", "webm", "bk2", "wav", "mp3", "ogg", "zip", "7z", "zst"]
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+impl CompressionSettings {
+    /// Picks the method and level for `compressor`, rejecting combinations the format can't write.
+    pub fn resolve(&self, compressor: &dyn Compressor) -> anyhow::Result<Compression> {
+        let methods = compressor.methods();
+        let method = self.method.unwrap_or(methods[0]);
+        if !methods.contains(&method) {
+            let known: Vec<_> = methods.iter().map(|m| m.name()).collect();
+            bail!(
+                "Compression method '{}' is not supported by {}, use one of: {}",
+                method, compressor.format(), known.join(", ")
+            );
+        }
+
+        let level = self.level.unwrap_or(method.default_level());
+        if !method.levels().contains(&level) {
+            bail!(
+                "Compression level {} is out of range for {}, expected {}..={}",
+                level, method, method.levels().start(), method.levels().end()
+            );
+        }
+
+        let threads = match self.threads {
+            Some(0) => bail!("Compression thread count must be at least 1"),
+            Some(threads) => threads,
+            None => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        };
+
+        let store_extensions = if compressor.stores_per_entry() {
+            self.store_extensions.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect()
+        } else {
+            Vec::new()
+        };
+
+        if self.password.is_some() && !compressor.encrypts() {
+            let known: Vec<_> = ArchiveFormat::ALL
+                .iter()
+                .filter(|f| f.compressor().encrypts())
+                .map(|f| f.extension())
+                .collect();
+            bail!("{} archives can't be encrypted, use one of: {}", compressor.format(), known.join(", "));
+        }
+
+        Ok(Compression { method, level, threads, store_extensions, password: self.password.clone() })
+    }
+}
+
+/// Resolved compression parameters handed to a [`Compressor`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compression {
+    pub method: CompressionMethod,
+    pub level: u32,
+    pub threads: usize,
+    pub store_extensions: Vec<String>,
+    pub password: Option<String>,
+}
+
+impl Compression {
+    /// Whether `file` is written without compression because of its extension.
+    pub fn stores(&self, file: &ProjectFile) -> bool {
+        match file.path.extension() {
+            Some(extension) => {
+                let extension = extension.to_string_lossy().to_lowercase();
+                self.store_extensions.contains(&extension)
+            }
+            None => false,
+        }
+    }
+}
+
+/// What `compress_project` did, shown in the push summary.
+#[derive(Debug, Clone)]
+pub struct CompressionReport {
+    pub format: ArchiveFormat,
+    pub method: CompressionMethod,
+    pub level: u32,
+    pub threads: usize,
+    pub input_bytes: u64,
+    pub stored_files: usize,
+    pub stored_bytes: u64,
+    pub encrypted: bool,
+}
+
+/// What [`Compressor::extract`] unpacks from an archive.
+#[derive(Clone, Default)]
+pub struct ExtractOptions {
+    /// Password of an encrypted archive.
+    pub password: Option<String>,
+    /// Entries to unpack; `None` unpacks the whole archive.
+    pub selection: Option<PathSelection>,
+    /// Exact names of the entries to unpack on top of `selection`, e.g. the unchanged files an
+    /// incremental backup takes from an earlier archive.
+    pub entries: Option<HashSet<String>>,
+    pub policy: OverwritePolicy,
+    /// Uncompressed size of the entries to unpack, shown as the progress total when known.
+    pub total_bytes: Option<u64>,
+}
+
+impl ExtractOptions {
+    /// Whether the entry named `name` is unpacked.
+    pub fn selects(&self, name: &str) -> bool {
+        let selected = match &self.selection {
+            Some(selection) => selection.matches(name),
+            None => true,
+        };
+        selected && self.entries.as_ref().is_none_or(|entries| entries.contains(name))
+    }
+}
+
+/// What extraction does when an entry would replace an existing local file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    /// Replace the local file.
+    #[default]
+    Overwrite,
+    /// Keep the local file and skip the entry.
+    Skip,
+    /// Keep the local file if it was modified after the entry, replace it otherwise.
+    Newer,
+    /// Keep the local file and write the entry next to it as `name (1).ext`.
+    Rename,
+    /// Move the local file to [`TRASH_DIR`] before replacing it.
+    Trash,
+}
+
+impl OverwritePolicy {
+    pub const ALL: [OverwritePolicy; 5] = [
+        OverwritePolicy::Overwrite,
+        OverwritePolicy::Skip,
+        OverwritePolicy::Newer,
+        OverwritePolicy::Rename,
+        OverwritePolicy::Trash,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OverwritePolicy::Overwrite => "overwrite",
+            OverwritePolicy::Skip => "skip",
+            OverwritePolicy::Newer => "newer",
+            OverwritePolicy::Rename => "rename",
+            OverwritePolicy::Trash => "trash",
+        }
+    }
+}
+
+impl fmt::Display for OverwritePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for OverwritePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match OverwritePolicy::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(s)) {
+            Some(policy) => Ok(policy),
+            None => {
+                let known: Vec<_> = OverwritePolicy::ALL.iter().map(|p| p.name()).collect();
+                bail!("Unknown overwrite policy '{}', expected one of: {}", s, known.join(", "))
+            }
+        }
+    }
+}
+
+/// What a backup does with symbolic links and junctions inside the project.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Store the link itself; a restore recreates it where the platform allows.
+    #[default]
+    Store,
+    /// Back up what the link points to as regular files and directories.
+    Follow,
+    /// Leave links out of the backup.
+    Skip,
+}
+
+impl SymlinkPolicy {
+    pub const ALL: [SymlinkPolicy; 3] = [SymlinkPolicy::Store, SymlinkPolicy::Follow, SymlinkPolicy::Skip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SymlinkPolicy::Store => "store",
+            SymlinkPolicy::Follow => "follow",
+            SymlinkPolicy::Skip => "skip",
+        }
+    }
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match SymlinkPolicy::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(s)) {
+            Some(policy) => Ok(policy),
+            None => {
+                let known: Vec<_> = SymlinkPolicy::ALL.iter().map(|p| p.name()).collect();
+                bail!("Unknown symlink policy '{}', expected one of: {}", s, known.join(", "))
+            }
+        }
+    }
+}
+
+/// What [`Compressor::extract`] did with the entries it unpacked.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractReport {
+    /// Files written, including renamed ones.
+    pub extracted: usize,
+    /// Entries skipped because the local file was kept.
+    pub kept: usize,
+    /// Entries written next to a local file under another name.
+    pub renamed: usize,
+    /// Local files moved to `trash_dir` before being replaced.
+    pub trashed: usize,
+    pub trash_dir: Option<PathBuf>,
+    /// Links recreated.
+    pub linked: usize,
+    /// Links that couldn't be recreated, e.g. without the privilege to create symlinks on Windows.
+    pub link_warnings: Vec<String>,
+}
+
+impl ExtractReport {
+    /// Adds up the reports of the archives a backup is restored from.
+    pub fn merge(&mut self, other: ExtractReport) {
+        self.extracted += other.extracted;
+        self.kept += other.kept;
+        self.renamed += other.renamed;
+        self.trashed += other.trashed;
+        self.trash_dir = self.trash_dir.take().or(other.trash_dir);
+        self.linked += other.linked;
+        self.link_warnings.extend(other.link_warnings);
+    }
+}
+
+/// What [`Compressor::verify`] found decoding an archive.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Files that decoded and matched the checksums the format stores.
+    pub verified: Vec<String>,
+    /// Uncompressed bytes decoded.
+    pub bytes: u64,
+    pub corrupt: Vec<CorruptEntry>,
+}
+
+/// Part of an archive that failed to decode.
+#[derive(Debug, Clone)]
+pub struct CorruptEntry {
+    /// Entry name; `None` when the archive structure itself is damaged, e.g. a 7z block
+    /// that can't be opened or the end of a zstd stream.
+    pub name: Option<String>,
+    pub error: String,
+}
+
+impl VerifyReport {
+    /// Adds what verifying another archive found, e.g. the base of an incremental backup.
+    pub fn merge(&mut self, other: VerifyReport) {
+        self.verified.extend(other.verified);
+        self.bytes += other.bytes;
+        self.corrupt.extend(other.corrupt);
+    }
+
+    /// Decodes `data` to the end through `progress`, recording the entry as verified or corrupt.
+    /// Returns whether it decoded.
+    pub(crate) fn check(&mut self, name: &str, data: &mut dyn Read, progress: &Progress) -> bool {
+        match io::copy(&mut progress.reader(name, data), &mut io::sink()) {
+            Ok(bytes) => {
+                self.bytes += bytes;
+                self.verified.push(name.to_string());
+                true
+            }
+            Err(e) => {
+                self.corrupt.push(CorruptEntry { name: Some(name.to_string()), error: e.to_string() });
+                false
+            }
+        }
+    }
+
+    fn damaged(&mut self, name: Option<&str>, error: impl fmt::Display) {
+        self.corrupt.push(CorruptEntry { name: name.map(str::to_string), error: error.to_string() });
+    }
+}
+
+/// Returned by [`Compressor::extract`] when an encrypted archive is opened without the right password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordError {
+    Required,
+    Wrong,
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordError::Required => f.write_str("Archive is encrypted, a password is required"),
+            PasswordError::Wrong => f.write_str("Wrong archive password"),
+        }
+    }
+}
+
+impl std::error::Error for PasswordError {}
+
+/// Result of [`compress_project`].
+pub struct CompressedProject {
+    pub files: Vec<ProjectFile>,
+    /// Archive files in order: the output path itself, or its `.001`, `.002`, ... volumes.
+    pub volumes: Vec<PathBuf>,
+    pub report: CompressionReport,
+}
+
+/// Entry of an archive as read by [`Compressor::list`].
+#[derive(Debug, Clone)]
+pub struct ArchiveEntryInfo {
+    pub path: String,
+    /// Uncompressed size, 0 for directories.
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_dir: bool,
+    /// Whether the entry is a stored link rather than a file.
+    pub is_link: bool,
+}
+
+/// Seekable source a [`Compressor`] lists entries from, e.g. a local file or
+/// [`RemoteArchive`](crate::tools::remote_archive::RemoteArchive).
+pub trait ArchiveInput: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ArchiveInput for T {}
+
+/// Seekable destination a [`Compressor`] writes the archive to, see [`VolumeWriter`].
+pub trait ArchiveOutput: Write + Seek + Send {}
+
+impl<T: Write + Seek + Send> ArchiveOutput for T {}
+
+/// Archive format backend. Implementations are blocking and may spawn their own threads;
+/// [`compress_project`] and [`extract_archive`] run them on the blocking pool and own the progress output.
+pub trait Compressor: Send + Sync {
+    fn format(&self) -> ArchiveFormat;
+
+    /// Methods this format can write; the first one is the default.
+    fn methods(&self) -> &'static [CompressionMethod];
+
+    /// Whether single entries can be stored uncompressed, see [`Compression::stores`].
+    fn stores_per_entry(&self) -> bool {
+        true
+    }
+
+    /// Whether archives can be encrypted with [`Compression::password`].
+    fn encrypts(&self) -> bool {
+        false
+    }
+
+    /// Whether entry names are encrypted along with the data, so listing needs the password.
+    fn encrypts_names(&self) -> bool {
+        false
+    }
+
+    /// Whether entries can be listed from an index without decoding the archive, see [`Compressor::list`].
+    fn has_index(&self) -> bool {
+        true
+    }
+
+    /// Writes `contents` to `output`, naming every entry after its `relative_path` with its
+    /// [`EntryMetadata`], and reading the files through `progress`. Links are stored the way the
+    /// format's own tools store them, so they extract as links there too.
+    /// `output_path` is where the archive ends up, temporary files may be created next to it.
+    fn compress(
+        &self,
+        contents: &ProjectContents,
+        output: &mut dyn ArchiveOutput,
+        output_path: &Path,
+        compression: &Compression,
+        progress: &Progress,
+    ) -> anyhow::Result<()>;
+
+    /// Unpacks the entries of `archive_path` picked by `options` under `extract_path` through an
+    /// [`EntryWriter`], failing with [`PasswordError`] when the archive is encrypted and the password
+    /// is missing or wrong.
+    fn extract(
+        &self,
+        archive_path: &Path,
+        extract_path: &Path,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> anyhow::Result<ExtractReport>;
+
+    /// Decodes the file entries of `archive_path` picked by `options` without writing them anywhere,
+    /// checking the checksums the format stores. Entries that fail are collected in the report;
+    /// an archive that can't be opened or a [`PasswordError`] fails the whole call.
+    fn verify(&self, archive_path: &Path, options: &ExtractOptions, progress: &Progress) -> anyhow::Result<VerifyReport>;
+
+    /// Reads the entries of the archive in `input`. Formats with an index read only the index,
+    /// others decode the whole stream.
+    fn list(&self, input: &mut dyn ArchiveInput, password: Option<&str>) -> anyhow::Result<Vec<ArchiveEntryInfo>>;
+}
+
+/// Walks the project tree and returns every file that goes into a backup.
+/// Ignored directories are pruned, so their contents are never visited.
+pub fn collect_project_files(
+    project_path: &Path,
+    profile: &BackupProfile,
+    symlinks: SymlinkPolicy,
+) -> anyhow::Result<Vec<ProjectFile>> {
+    Ok(collect_project(project_path, profile, symlinks)?.files)
+}
+
+/// Like [`collect_project_files`], also returning the empty directories, which would otherwise
+/// vanish on restore, and the links kept under [`SymlinkPolicy::Store`]. Creating an empty
+/// directory recreates its parents, so only those are stored. Fails with
+/// [`UnportablePaths`](crate::tools::path_names::UnportablePaths) when a name couldn't be
+/// restored on another platform.
+pub fn collect_project(project_path: &Path, profile: &BackupProfile, symlinks: SymlinkPolicy) -> anyhow::Result<ProjectContents> {
+    let rules = IgnoreRules::load(project_path, profile)?;
+    let mut files = Vec::new();
+    let mut empty_dirs = Vec::new();
+    let mut links = Vec::new();
+    let mut names = PortableNames::default();
+
+    // Link loops under `Follow` come out as walk errors and are skipped like unreadable entries
+    let walker = WalkDir::new(project_path).follow_links(symlinks == SymlinkPolicy::Follow);
+    let walker = walker.into_iter().filter_entry(|entry| {
+        // A link to a directory is matched as one, so `Saved/` also leaves out a linked `Saved`
+        let is_dir = entry.file_type().is_dir() || (entry.path_is_symlink() && entry.path().is_dir());
+        match entry.path().strip_prefix(project_path) {
+            Ok(relative_path) if relative_path.as_os_str().is_empty() => true,
+            Ok(relative_path) => !rules.is_ignored(relative_path, is_dir),
+            Err(_) => false,
+        }
+    });
+
+    for entry in walker.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let relative_path = path.strip_prefix(project_path)?;
+        if relative_path.as_os_str().is_empty() || !rules.is_included(relative_path) {
+            continue;
+        }
+        if entry.path_is_symlink() && symlinks == SymlinkPolicy::Skip {
+            continue;
+        }
+        let relative = names.add(relative_path);
+        if entry.path_is_symlink() && symlinks != SymlinkPolicy::Follow {
+            let target = fs::read_link(path)?.to_string_lossy().replace('\\', "/");
+            links.push(ProjectLink { path: path.to_path_buf(), relative_path: relative, target });
+        } else if path.is_file() {
+            files.push(ProjectFile {
+                path: path.to_path_buf(),
+                relative_path: relative,
+                size: fs::metadata(path)?.len(),
+            });
+        } else if entry.file_type().is_dir() && fs::read_dir(path)?.next().is_none() {
+            empty_dirs.push(ProjectDir { path: path.to_path_buf(), relative_path: relative });
+        }
+    }
+
+    names.check()?;
+    Ok(ProjectContents { files, empty_dirs, links })
+}
+
+/// Compresses the project into `output_path`, or into `<output_path>.001`, `.002`, ...
+/// volumes of at most `volume_size` bytes each.
+pub async fn compress_project(
+    project_path: &Path,
+    output_path: &Path,
+    profile: &BackupProfile,
+    symlinks: SymlinkPolicy,
+    format: ArchiveFormat,
+    settings: &CompressionSettings,
+    volume_size: Option<u64>,
+) -> anyhow::Result<CompressedProject> {
+    let contents = collect_project_with_spinner(project_path, profile, symlinks)?;
+    compress_contents(contents, output_path, format, settings, volume_size).await
+}
+
+/// [`collect_project`] with a spinner, since walking a big project takes a while.
+pub fn collect_project_with_spinner(
+    project_path: &Path,
+    profile: &BackupProfile,
+    symlinks: SymlinkPolicy,
+) -> anyhow::Result<ProjectContents> {
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+            .template("{spinner} {msg}")?,
+    );
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb.set_message("Counting files...");
+
+    let contents = collect_project(project_path, profile, symlinks);
+    pb.finish_and_clear();
+    contents
+}
+
+/// Like [`compress_project`] for contents that are already collected, e.g. only the files an
+/// incremental push uploads.
+pub async fn compress_contents(
+    contents: ProjectContents,
+    output_path: &Path,
+    format: ArchiveFormat,
+    settings: &CompressionSettings,
+    volume_size: Option<u64>,
+) -> anyhow::Result<CompressedProject> {
+    let compressor = format.compressor();
+    let compression = settings.resolve(compressor.as_ref())?;
+    println!(
+        "Starting compression ({}, {} level {}, {} threads)...",
+        format, compression.method, compression.level, compression.threads
+    );
+
+    let progress = Progress::new("Compressing", Some(contents.files.iter().map(|f| f.size).sum()));
+    let output_path_buf = output_path.to_path_buf();
+    let (files, compression, volumes) = tokio::task::spawn_blocking(move || {
+        let mut output = VolumeWriter::create(&output_path_buf, volume_size)?;
+        if let Err(e) = compressor.compress(&contents, &mut output, &output_path_buf, &compression, &progress) {
+            progress.abandon();
+            return Err(e);
+        }
+        let volumes = output.finish()?;
+        progress.finish("Compression complete!");
+        anyhow::Ok((contents.files, compression, volumes))
+    })
+    .await??;
+
+    if volumes.len() > 1 {
+        println!(
+            "Archive created successfully at: {} ({} volumes)",
+            output_path.display(),
+            volumes.len()
+        );
+    } else {
+        println!(
+            "Archive created successfully at: {}",
+            output_path.display()
+        );
+    }
+
+    let stored: Vec<_> = files.iter().filter(|f| compression.stores(f)).collect();
+    let report = CompressionReport {
+        format,
+        method: compression.method,
+        level: compression.level,
+        threads: compression.threads,
+        input_bytes: files.iter().map(|f| f.size).sum(),
+        stored_files: stored.len(),
+        stored_bytes: stored.iter().map(|f| f.size).sum(),
+        encrypted: compression.password.is_some(),
+    };
+    Ok(CompressedProject { files, volumes, report })
+}
+
+pub async fn extract_archive(
+    archive_path: &Path,
+    extract_path: &Path,
+    format: ArchiveFormat,
+    options: &ExtractOptions,
+) -> anyhow::Result<ExtractReport> {
+    println!("Starting extraction ({})...", format);
+
+    if !extract_path.exists() {
+        tokio::fs::create_dir_all(extract_path).await?;
+    }
+
+    let progress = Progress::new("Extracting", options.total_bytes);
+    let (archive, target, options) = (archive_path.to_path_buf(), extract_path.to_path_buf(), options.clone());
+    let task_progress = progress.clone();
+    let extracted = tokio::task::spawn_blocking(move || format.compressor().extract(&archive, &target, &options, &task_progress)).await?;
+    let report = match extracted {
+        Ok(report) => report,
+        Err(e) => {
+            progress.abandon();
+            return Err(e);
+        }
+    };
+
+    progress.finish("Extraction complete!");
+    println!("Archive successfully extracted to: {}", extract_path.display());
+    Ok(report)
+}
+
+pub async fn verify_archive(archive_path: &Path, format: ArchiveFormat, options: &ExtractOptions) -> anyhow::Result<VerifyReport> {
+    println!("Verifying archive ({})...", format);
+
+    let progress = Progress::new("Verifying", options.total_bytes);
+    let (archive, options) = (archive_path.to_path_buf(), options.clone());
+    let task_progress = progress.clone();
+    let verified = tokio::task::spawn_blocking(move || format.compressor().verify(&archive, &options, &task_progress)).await?;
+    match verified {
+        Ok(report) => {
+            progress.finish("Verification complete!");
+            Ok(report)
+        }
+        Err(e) => {
+            progress.abandon();
+            Err(e)
+        }
+    }
+}
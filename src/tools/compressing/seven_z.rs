@@ -0,0 +1,327 @@
+use std::fs;
+use std::io::{self, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use sevenz_rust2::encoder_options::{AesEncoderOptions, Lzma2Options, LzmaOptions};
+use sevenz_rust2::{
+    prepare_block, Archive, ArchiveEntry, ArchiveReader, ArchiveWriter, EncoderConfiguration, EncoderMethod, Password, PreparedBlock,
+    SourceReader,
+};
+use super::{
+    ArchiveEntryInfo, ArchiveFormat, ArchiveInput, ArchiveOutput, Compression, CompressionMethod, Compressor, EntryMetadata,
+    EntryWriter, ExtractOptions, ExtractReport, PasswordError, ProjectContents, ProjectFile, ProjectLink, VerifyReport,
+};
+use crate::tools::progress::Progress;
+
+/// Files up to this size are packed into solid blocks of about this much input, compressed in parallel.
+/// Bigger files get a block of their own, split into LZMA2 chunks that are compressed in parallel.
+const BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+const LZMA2_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// 7-Zip keeps the Unix mode in the high 16 bits of the Windows attributes when this bit is set.
+const UNIX_EXTENSION: u32 = 0x8000;
+const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+const FILE_ATTRIBUTE_ARCHIVE: u32 = 0x20;
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+pub struct SevenZCompressor;
+
+impl Compressor for SevenZCompressor {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::SevenZ
+    }
+
+    fn methods(&self) -> &'static [CompressionMethod] {
+        &[CompressionMethod::Lzma2, CompressionMethod::Lzma, CompressionMethod::Store]
+    }
+
+    fn encrypts(&self) -> bool {
+        true
+    }
+
+    fn compress(
+        &self,
+        contents: &ProjectContents,
+        output: &mut dyn ArchiveOutput,
+        _output_path: &Path,
+        compression: &Compression,
+        progress: &Progress,
+    ) -> anyhow::Result<()> {
+        let mut writer = ArchiveWriter::new(BufWriter::new(output))?;
+        let (stored, packed): (Vec<_>, Vec<_>) = contents
+            .files
+            .iter()
+            .partition(|f| compression.method == CompressionMethod::Store || compression.stores(f));
+        let (large, small): (Vec<_>, Vec<_>) = packed.into_iter().partition(|f| f.size > BLOCK_SIZE);
+
+        for file in stored {
+            writer.set_content_methods(content_methods(compression, EncoderMethod::COPY.into()));
+            push_file(&mut writer, file, progress)?;
+        }
+
+        // Like 7-Zip on Unix, a link is an entry with the link mode whose contents are the target
+        for link in &contents.links {
+            writer.set_content_methods(content_methods(compression, EncoderMethod::COPY.into()));
+            writer.push_archive_entry(link_entry(link), Some(link.target.as_bytes()))?;
+        }
+
+        for file in large {
+            writer.set_content_methods(content_methods(compression, method_configuration(compression, compression.threads)));
+            push_file(&mut writer, file, progress)?;
+        }
+
+        for dir in &contents.empty_dirs {
+            writer.push_archive_entry::<&[u8]>(entry_for(&dir.path, &dir.relative_path)?, None)?;
+        }
+
+        let blocks = solid_blocks(small);
+        for wave in blocks.chunks(compression.threads) {
+            let prepared: Vec<_> = thread::scope(|scope| {
+                let workers: Vec<_> = wave
+                    .iter()
+                    .map(|block| {
+                        let methods = Arc::new(content_methods(compression, method_configuration(compression, 1)));
+                        scope.spawn(|| prepare(methods, block, progress))
+                    })
+                    .collect();
+                workers.into_iter().map(|w| w.join().expect("compression thread panicked")).collect()
+            });
+            for block in prepared {
+                writer.push_prepared_block(block?)?;
+            }
+        }
+
+        // The header is encrypted with the content methods that are set when the archive is finished
+        writer.set_content_methods(content_methods(compression, method_configuration(compression, 1)));
+        writer.finish()?.flush()?;
+        Ok(())
+    }
+
+    fn encrypts_names(&self) -> bool {
+        true
+    }
+
+    fn extract(
+        &self,
+        archive_path: &Path,
+        extract_path: &Path,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> anyhow::Result<ExtractReport> {
+        let password = options.password.as_deref().map(Password::new).unwrap_or_else(Password::empty);
+        let mut reader = ArchiveReader::open(archive_path, password).map_err(password_error)?;
+        let mut writer = EntryWriter::new(extract_path, options.policy, progress);
+
+        let mut failure = None;
+        reader
+            .for_each_entries(|entry, data| {
+                let result = if !options.selects(entry.name()) {
+                    // Entries of a block share one decoder, so a skipped entry still has to be read through
+                    io::copy(data, &mut io::sink()).map(|_| ()).map_err(Into::into)
+                } else if entry.is_directory() {
+                    writer.create_dir(entry.name())
+                } else if is_link(entry) {
+                    read_target(data).and_then(|target| writer.add_link(entry.name(), &target))
+                } else {
+                    writer.write_file(entry.name(), &entry_metadata(entry), data)
+                };
+                match result {
+                    Ok(()) => Ok(true),
+                    Err(e) => {
+                        failure = Some(e);
+                        Ok(false)
+                    }
+                }
+            })
+            .map_err(password_error)?;
+
+        match failure {
+            Some(e) => Err(e),
+            None => writer.finish(),
+        }
+    }
+
+    fn verify(&self, archive_path: &Path, options: &ExtractOptions, progress: &Progress) -> anyhow::Result<VerifyReport> {
+        let password = options.password.as_deref().map(Password::new).unwrap_or_else(Password::empty);
+        let mut reader = ArchiveReader::open(archive_path, password).map_err(password_error)?;
+        let mut report = VerifyReport::default();
+
+        // Entries are read through a CRC-checking reader, so a damaged entry fails while it's decoded
+        let decoded = reader.for_each_entries(|entry, data| {
+            if entry.is_directory() {
+                return Ok(true);
+            }
+            if options.selects(entry.name()) {
+                report.check(entry.name(), data, progress);
+            } else {
+                io::copy(data, &mut io::sink()).ok();
+            }
+            Ok(true)
+        });
+        if let Err(e) = decoded.map_err(password_error) {
+            if e.is::<PasswordError>() {
+                return Err(e);
+            }
+            report.damaged(None, e);
+        }
+        Ok(report)
+    }
+
+    fn list(&self, mut input: &mut dyn ArchiveInput, password: Option<&str>) -> anyhow::Result<Vec<ArchiveEntryInfo>> {
+        let password = password.map(Password::new).unwrap_or_else(Password::empty);
+        // Only the start header and the header at the end of the archive are read
+        let archive = Archive::read(&mut input, &password).map_err(password_error)?;
+        Ok(archive
+            .files
+            .iter()
+            .map(|entry| ArchiveEntryInfo {
+                path: entry.name().to_string(),
+                size: entry.size(),
+                modified: entry.has_last_modified_date.then(|| entry.last_modified_date.into()),
+                is_dir: entry.is_directory(),
+                is_link: is_link(entry),
+            })
+            .collect())
+    }
+}
+
+fn password_error(error: sevenz_rust2::Error) -> anyhow::Error {
+    match error {
+        sevenz_rust2::Error::PasswordRequired => PasswordError::Required.into(),
+        sevenz_rust2::Error::MaybeBadPassword(_) => PasswordError::Wrong.into(),
+        e => e.into(),
+    }
+}
+
+fn method_configuration(compression: &Compression, threads: usize) -> EncoderConfiguration {
+    match compression.method {
+        CompressionMethod::Lzma => LzmaOptions::from_level(compression.level).into(),
+        _ if threads > 1 => Lzma2Options::from_level_mt(compression.level, threads as u32, LZMA2_CHUNK_SIZE).into(),
+        _ => Lzma2Options::from_level(compression.level).into(),
+    }
+}
+
+/// Prepends AES-256 to `method` when the archive is encrypted. Every call picks a fresh salt and IV,
+/// so no two blocks are encrypted with the same key stream.
+fn content_methods(compression: &Compression, method: EncoderConfiguration) -> Vec<EncoderConfiguration> {
+    match &compression.password {
+        Some(password) => vec![AesEncoderOptions::new(Password::new(password)).into(), method],
+        None => vec![method],
+    }
+}
+
+fn push_file<W: Write + Seek>(writer: &mut ArchiveWriter<W>, file: &ProjectFile, progress: &Progress) -> anyhow::Result<()> {
+    let entry = entry_for(&file.path, &file.relative_path)?;
+    writer.push_archive_entry(entry, Some(progress.reader(&file.relative_path, fs::File::open(&file.path)?)))?;
+    Ok(())
+}
+
+/// Entry for the file or directory at `path` with its mtime and permissions.
+fn entry_for(path: &Path, name: &str) -> io::Result<ArchiveEntry> {
+    let mut entry = ArchiveEntry::from_path(path, name.to_string());
+    let metadata = fs::metadata(path)?;
+    if let Some(mode) = EntryMetadata::from_fs(&metadata).mode {
+        let (kind, file_type) = if metadata.is_dir() { (FILE_ATTRIBUTE_DIRECTORY, 0o040000) } else { (FILE_ATTRIBUTE_ARCHIVE, 0o100000) };
+        let readonly = if mode & 0o222 == 0 { FILE_ATTRIBUTE_READONLY } else { 0 };
+        entry.has_windows_attributes = true;
+        entry.windows_attributes = kind | readonly | UNIX_EXTENSION | ((file_type | mode) << 16);
+    }
+    Ok(entry)
+}
+
+fn link_entry(link: &ProjectLink) -> ArchiveEntry {
+    let mut entry = ArchiveEntry::new_file(&link.relative_path);
+    let modified = fs::symlink_metadata(&link.path).and_then(|m| m.modified()).ok();
+    if let Some(modified) = modified.and_then(|m| m.try_into().ok()) {
+        entry.has_last_modified_date = true;
+        entry.last_modified_date = modified;
+    }
+    entry.has_windows_attributes = true;
+    entry.windows_attributes = FILE_ATTRIBUTE_ARCHIVE | UNIX_EXTENSION | ((S_IFLNK | 0o777) << 16);
+    entry
+}
+
+fn is_link(entry: &ArchiveEntry) -> bool {
+    let attributes = entry.windows_attributes;
+    entry.has_windows_attributes && attributes & UNIX_EXTENSION != 0 && (attributes >> 16) & S_IFMT == S_IFLNK
+}
+
+fn read_target(data: &mut dyn Read) -> anyhow::Result<String> {
+    let mut target = String::new();
+    data.read_to_string(&mut target)?;
+    Ok(target)
+}
+
+fn entry_metadata(entry: &ArchiveEntry) -> EntryMetadata {
+    let attributes = entry.windows_attributes;
+    let mode = match entry.has_windows_attributes {
+        true if attributes & UNIX_EXTENSION != 0 => Some((attributes >> 16) & 0o7777),
+        true if attributes & FILE_ATTRIBUTE_READONLY != 0 => Some(0o444),
+        _ => None,
+    };
+    EntryMetadata {
+        modified: entry.has_last_modified_date.then(|| entry.last_modified_date.into()),
+        mode,
+    }
+}
+
+fn solid_blocks(files: Vec<&ProjectFile>) -> Vec<Vec<&ProjectFile>> {
+    let mut blocks: Vec<Vec<&ProjectFile>> = Vec::new();
+    let mut block_size = 0;
+
+    for file in files {
+        if blocks.is_empty() || block_size + file.size > BLOCK_SIZE {
+            blocks.push(Vec::new());
+            block_size = 0;
+        }
+        block_size += file.size;
+        blocks.last_mut().unwrap().push(file);
+    }
+
+    blocks
+}
+
+fn prepare(
+    methods: Arc<Vec<EncoderConfiguration>>,
+    block: &[&ProjectFile],
+    progress: &Progress,
+) -> anyhow::Result<PreparedBlock> {
+    let entries = block
+        .iter()
+        .map(|f| entry_for(&f.path, &f.relative_path))
+        .collect::<io::Result<_>>()?;
+    let readers = block
+        .iter()
+        .map(|f| SourceReader::new(progress.reader(&f.relative_path, LazyFile { path: &f.path, file: None, done: false })))
+        .collect();
+
+    Ok(prepare_block(methods, entries, readers)?)
+}
+
+/// Opens the file on first read, so a block of many small files doesn't hold all of them open.
+struct LazyFile<'a> {
+    path: &'a Path,
+    file: Option<fs::File>,
+    done: bool,
+}
+
+impl Read for LazyFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done {
+            return Ok(0);
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(fs::File::open(self.path)?),
+        };
+        let n = file.read(buf)?;
+        if n == 0 {
+            self.done = true;
+            self.file = None;
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+use tar::EntryType;
+use super::{
+    ArchiveEntryInfo, ArchiveFormat, ArchiveInput, ArchiveOutput, Compression, CompressionMethod, Compressor, EntryMetadata,
+    EntryWriter, ExtractOptions, ExtractReport, ProjectContents, VerifyReport,
+};
+use crate::tools::progress::Progress;
+
+/// Tarball compressed as a single zstd stream. Store-only rules don't apply here,
+/// zstd emits raw blocks for incompressible data on its own.
+pub struct TarZstdCompressor;
+
+impl Compressor for TarZstdCompressor {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::TarZstd
+    }
+
+    fn methods(&self) -> &'static [CompressionMethod] {
+        &[CompressionMethod::Zstd]
+    }
+
+    fn stores_per_entry(&self) -> bool {
+        false
+    }
+
+    fn has_index(&self) -> bool {
+        false
+    }
+
+    fn compress(
+        &self,
+        contents: &ProjectContents,
+        output: &mut dyn ArchiveOutput,
+        _output_path: &Path,
+        compression: &Compression,
+        progress: &Progress,
+    ) -> anyhow::Result<()> {
+        let mut encoder = zstd::Encoder::new(BufWriter::new(output), compression.level as i32)?;
+        // Lets `verify` detect damage the tar format itself has no checksums for
+        encoder.include_checksum(true)?;
+        if compression.threads > 1 {
+            encoder.multithread(compression.threads as u32)?;
+        }
+        let mut builder = tar::Builder::new(encoder);
+
+        for file in &contents.files {
+            let source = fs::File::open(&file.path)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&source.metadata()?);
+            builder.append_data(&mut header, &file.relative_path, progress.reader(&file.relative_path, source))?;
+        }
+
+        // `set_metadata` records the mtime and mode, and makes directory headers for directories
+        for dir in &contents.empty_dirs {
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&fs::metadata(&dir.path)?);
+            builder.append_data(&mut header, &dir.relative_path, io::empty())?;
+        }
+
+        for link in &contents.links {
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&fs::symlink_metadata(&link.path)?);
+            header.set_entry_type(EntryType::Symlink);
+            header.set_size(0);
+            builder.append_link(&mut header, &link.relative_path, &link.target)?;
+        }
+
+        builder.into_inner()?.finish()?.flush()?;
+        Ok(())
+    }
+
+    fn extract(
+        &self,
+        archive_path: &Path,
+        extract_path: &Path,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> anyhow::Result<ExtractReport> {
+        let decoder = zstd::Decoder::new(fs::File::open(archive_path)?)?;
+        let mut archive = tar::Archive::new(decoder);
+        let mut writer = EntryWriter::new(extract_path, options.policy, progress);
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if !options.selects(&name) {
+                continue;
+            }
+            match entry.header().entry_type() {
+                EntryType::Directory => writer.create_dir(&name)?,
+                EntryType::Regular | EntryType::Continuous => {
+                    let metadata = EntryMetadata {
+                        modified: entry.header().mtime().ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+                        mode: entry.header().mode().ok().map(|mode| mode & 0o7777),
+                    };
+                    writer.write_file(&name, &metadata, &mut entry)?;
+                }
+                EntryType::Symlink => {
+                    if let Some(target) = entry.link_name()? {
+                        writer.add_link(&name, &target.to_string_lossy())?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        writer.finish()
+    }
+
+    fn verify(&self, archive_path: &Path, options: &ExtractOptions, progress: &Progress) -> anyhow::Result<VerifyReport> {
+        let decoder = zstd::Decoder::new(fs::File::open(archive_path)?)?;
+        let mut archive = tar::Archive::new(decoder);
+        let mut report = VerifyReport::default();
+
+        // A damaged zstd stream can't be resynchronised, so verification stops at the first error
+        let mut entries = archive.entries()?;
+        loop {
+            let mut entry = match entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => {
+                    report.damaged(None, e);
+                    return Ok(report);
+                }
+                None => break,
+            };
+            let name = entry.path()?.to_string_lossy().into_owned();
+            let is_file = matches!(entry.header().entry_type(), EntryType::Regular | EntryType::Continuous);
+            if is_file && options.selects(&name) && !report.check(&name, &mut entry, progress) {
+                return Ok(report);
+            }
+        }
+
+        // The frame checksum is only compared once the decoder reaches the end of the stream
+        if let Err(e) = io::copy(&mut archive.into_inner(), &mut io::sink()) {
+            report.damaged(None, e);
+        }
+        Ok(report)
+    }
+
+    fn list(&self, input: &mut dyn ArchiveInput, _password: Option<&str>) -> anyhow::Result<Vec<ArchiveEntryInfo>> {
+        let mut archive = tar::Archive::new(zstd::Decoder::new(input)?);
+        let mut entries = Vec::new();
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let kind = entry.header().entry_type();
+            if !matches!(kind, EntryType::Directory | EntryType::Regular | EntryType::Continuous | EntryType::Symlink) {
+                continue;
+            }
+            entries.push(ArchiveEntryInfo {
+                path: entry.path()?.to_string_lossy().trim_end_matches('/').to_string(),
+                size: entry.size(),
+                modified: entry.header().mtime().ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+                is_dir: kind == EntryType::Directory,
+                is_link: kind == EntryType::Symlink,
+            });
+        }
+        Ok(entries)
+    }
+}
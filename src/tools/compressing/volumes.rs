@@ -0,0 +1,96 @@
+use std::fs;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Archive output that is cut into files of at most `volume_size` bytes named `<output>.001`, `<output>.002`, ...
+/// Without a volume size everything goes to `output` itself. Seeking back across volumes is supported,
+/// since 7z and zip patch their headers after the data is written.
+pub struct VolumeWriter {
+    output: PathBuf,
+    volume_size: Option<u64>,
+    volumes: Vec<(PathBuf, fs::File)>,
+    position: u64,
+}
+
+impl VolumeWriter {
+    pub fn create(output: &Path, volume_size: Option<u64>) -> io::Result<Self> {
+        if volume_size == Some(0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "volume size must be positive"));
+        }
+
+        let mut writer = VolumeWriter {
+            output: output.to_path_buf(),
+            volume_size,
+            volumes: Vec::new(),
+            position: 0,
+        };
+        writer.open_volume(0)?;
+        Ok(writer)
+    }
+
+    pub fn volume_path(output: &Path, index: usize) -> PathBuf {
+        let mut name = output.as_os_str().to_owned();
+        name.push(format!(".{:03}", index + 1));
+        PathBuf::from(name)
+    }
+
+    /// Flushes every volume and returns their paths in order.
+    pub fn finish(mut self) -> io::Result<Vec<PathBuf>> {
+        for (_, file) in &mut self.volumes {
+            file.flush()?;
+        }
+        Ok(self.volumes.into_iter().map(|(path, _)| path).collect())
+    }
+
+    fn open_volume(&mut self, index: usize) -> io::Result<()> {
+        while self.volumes.len() <= index {
+            let path = match self.volume_size {
+                Some(_) => Self::volume_path(&self.output, self.volumes.len()),
+                None => self.output.clone(),
+            };
+            let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+            self.volumes.push((path, file));
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        let mut len = 0;
+        for (_, file) in &self.volumes {
+            len += file.metadata()?.len();
+        }
+        Ok(len)
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (index, offset, room) = match self.volume_size {
+            Some(size) => ((self.position / size) as usize, self.position % size, size - self.position % size),
+            None => (0, self.position, u64::MAX),
+        };
+        self.open_volume(index)?;
+
+        let file = &mut self.volumes[index].1;
+        file.seek(SeekFrom::Start(offset))?;
+        let n = file.write(&buf[..room.min(buf.len() as u64) as usize])?;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.volumes.iter_mut().try_for_each(|(_, file)| file.flush())
+    }
+}
+
+impl Seek for VolumeWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len()?.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the archive"))?;
+        Ok(self.position)
+    }
+}
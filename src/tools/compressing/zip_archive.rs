@@ -0,0 +1,260 @@
+use std::fs;
+use std::io::{self, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::SystemTime;
+use chrono::{Local, NaiveDateTime};
+use zip::write::SimpleFileOptions;
+use zip::result::ZipError;
+use zip::{AesMode, CompressionMethod, DateTime, ZipArchive, ZipWriter};
+use super::{
+    ArchiveEntryInfo, ArchiveFormat, ArchiveInput, ArchiveOutput, Compression, CompressionMethod as Method, Compressor,
+    EntryMetadata, EntryWriter, ExtractOptions, ExtractReport, PasswordError, ProjectContents, ProjectDir, ProjectFile, ProjectLink,
+    VerifyReport,
+};
+use crate::tools::progress::Progress;
+
+/// Zip entries are compressed independently, so every thread writes its own part archive
+/// and the parts are merged into the output without recompressing.
+pub struct ZipCompressor;
+
+impl Compressor for ZipCompressor {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Zip
+    }
+
+    fn methods(&self) -> &'static [Method] {
+        &[Method::Deflate, Method::Zstd, Method::Bzip2, Method::Store]
+    }
+
+    fn encrypts(&self) -> bool {
+        true
+    }
+
+    fn compress(
+        &self,
+        contents: &ProjectContents,
+        output: &mut dyn ArchiveOutput,
+        output_path: &Path,
+        compression: &Compression,
+        progress: &Progress,
+    ) -> anyhow::Result<()> {
+        let ProjectContents { files, empty_dirs, links } = contents;
+        let groups = balance(files, compression.threads);
+        if groups.len() <= 1 {
+            let files: Vec<_> = files.iter().collect();
+            return write_part(&files, empty_dirs, links, BufWriter::new(output), compression, progress);
+        }
+
+        let parts: Vec<PathBuf> = (0..groups.len())
+            .map(|i| output_path.with_extension(format!("part{}.zip", i)))
+            .collect();
+        let results: Vec<_> = thread::scope(|scope| {
+            let workers: Vec<_> = groups
+                .iter()
+                .zip(&parts)
+                .enumerate()
+                .map(|(i, (group, part))| {
+                    let (dirs, links) = if i == 0 { (&empty_dirs[..], &links[..]) } else { (&[][..], &[][..]) };
+                    scope.spawn(move || write_part(group, dirs, links, fs::File::create(part)?, compression, progress))
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().expect("compression thread panicked")).collect()
+        });
+
+        let merged = results.into_iter().collect::<anyhow::Result<()>>().and_then(|_| {
+            let mut writer = ZipWriter::new(BufWriter::new(output));
+            for part in &parts {
+                writer.merge_archive(ZipArchive::new(fs::File::open(part)?)?)?;
+            }
+            writer.finish()?.flush()?;
+            Ok(())
+        });
+
+        for part in &parts {
+            fs::remove_file(part).ok();
+        }
+        merged
+    }
+
+    fn extract(
+        &self,
+        archive_path: &Path,
+        extract_path: &Path,
+        options: &ExtractOptions,
+        progress: &Progress,
+    ) -> anyhow::Result<ExtractReport> {
+        let mut archive = ZipArchive::new(fs::File::open(archive_path)?)?;
+        let mut writer = EntryWriter::new(extract_path, options.policy, progress);
+
+        for i in 0..archive.len() {
+            let selected = match archive.name_for_index(i) {
+                Some(name) => options.selects(&name?),
+                None => false,
+            };
+            if !selected {
+                continue;
+            }
+            let entry = match options.password.as_deref() {
+                Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+                None => archive.by_index(i),
+            };
+            let mut entry = entry.map_err(password_error)?;
+            let name = entry.name()?.into_owned();
+            if entry.is_dir() {
+                writer.create_dir(&name)?;
+            } else if entry.is_symlink() {
+                let mut target = String::new();
+                entry.read_to_string(&mut target)?;
+                writer.add_link(&name, &target)?;
+            } else {
+                let metadata = EntryMetadata {
+                    modified: entry.last_modified().and_then(system_time),
+                    mode: entry.unix_mode().map(|mode| mode & 0o7777),
+                };
+                writer.write_file(&name, &metadata, &mut entry)?;
+            }
+        }
+        writer.finish()
+    }
+
+    fn verify(&self, archive_path: &Path, options: &ExtractOptions, progress: &Progress) -> anyhow::Result<VerifyReport> {
+        let mut archive = ZipArchive::new(fs::File::open(archive_path)?)?;
+        let mut report = VerifyReport::default();
+
+        for i in 0..archive.len() {
+            let name = match archive.name_for_index(i) {
+                Some(name) => name?.into_owned(),
+                None => continue,
+            };
+            if name.ends_with('/') || !options.selects(&name) {
+                continue;
+            }
+            let entry = match options.password.as_deref() {
+                Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+                None => archive.by_index(i),
+            };
+            // Reading an entry to the end checks its CRC, or the HMAC of an AES entry
+            match entry.map_err(password_error) {
+                Ok(mut entry) => {
+                    report.check(&name, &mut entry, progress);
+                }
+                Err(e) if e.is::<PasswordError>() => return Err(e),
+                Err(e) => report.damaged(Some(&name), e),
+            }
+        }
+        Ok(report)
+    }
+
+    fn list(&self, input: &mut dyn ArchiveInput, _password: Option<&str>) -> anyhow::Result<Vec<ArchiveEntryInfo>> {
+        // Only the central directory at the end of the archive is read, entry names aren't encrypted
+        let archive = ZipArchive::new(input)?;
+        (0..archive.len())
+            .map(|i| {
+                let entry = archive.by_index_data(i)?;
+                Ok(ArchiveEntryInfo {
+                    path: entry.name()?.trim_end_matches('/').to_string(),
+                    size: entry.size(),
+                    modified: entry.last_modified().and_then(system_time),
+                    is_dir: entry.is_dir(),
+                    is_link: entry.is_symlink(),
+                })
+            })
+            .collect()
+    }
+}
+
+fn password_error(error: ZipError) -> anyhow::Error {
+    match error {
+        ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => PasswordError::Required.into(),
+        ZipError::InvalidPassword => PasswordError::Wrong.into(),
+        e => e.into(),
+    }
+}
+
+fn write_part<W: Write + Seek>(
+    files: &[&ProjectFile],
+    empty_dirs: &[ProjectDir],
+    links: &[ProjectLink],
+    output: W,
+    compression: &Compression,
+    progress: &Progress,
+) -> anyhow::Result<()> {
+    let mut writer = ZipWriter::new(output);
+
+    for file in files {
+        let method = if compression.stores(file) { Method::Store } else { compression.method };
+        let (method, level) = match method {
+            Method::Zstd => (CompressionMethod::Zstd, Some(compression.level as i64)),
+            Method::Bzip2 => (CompressionMethod::Bzip2, Some(compression.level as i64)),
+            Method::Store => (CompressionMethod::Stored, None),
+            _ => (CompressionMethod::Deflated, Some(compression.level as i64)),
+        };
+        let mut options = entry_options(&file.path)?
+            .compression_method(method)
+            .compression_level(level)
+            .large_file(file.size >= u32::MAX as u64);
+        if let Some(password) = &compression.password {
+            options = options.with_aes_encryption(AesMode::Aes256, password);
+        }
+        writer.start_file(file.relative_path.as_str(), options)?;
+        io::copy(&mut progress.reader(&file.relative_path, fs::File::open(&file.path)?), &mut writer)?;
+    }
+
+    for dir in empty_dirs {
+        writer.add_directory(dir.relative_path.as_str(), entry_options(&dir.path)?)?;
+    }
+
+    // Stored with the Unix link mode and the target as contents, as Info-ZIP does
+    for link in links {
+        let mut options = SimpleFileOptions::default();
+        if let Some(modified) = fs::symlink_metadata(&link.path)?.modified().ok().and_then(zip_time) {
+            options = options.last_modified_time(modified);
+        }
+        if let Some(password) = &compression.password {
+            options = options.with_aes_encryption(AesMode::Aes256, password);
+        }
+        writer.add_symlink(link.relative_path.as_str(), link.target.as_str(), options)?;
+    }
+
+    writer.finish()?.flush()?;
+    Ok(())
+}
+
+/// Options carrying the mtime and permissions of the file or directory at `path`.
+fn entry_options(path: &Path) -> io::Result<SimpleFileOptions> {
+    let metadata = EntryMetadata::from_fs(&fs::metadata(path)?);
+    let mut options = SimpleFileOptions::default();
+    if let Some(modified) = metadata.modified.and_then(zip_time) {
+        options = options.last_modified_time(modified);
+    }
+    if let Some(mode) = metadata.mode {
+        options = options.unix_permissions(mode);
+    }
+    Ok(options)
+}
+
+/// Zip stores local time with two-second precision and no time zone.
+fn zip_time(time: SystemTime) -> Option<DateTime> {
+    DateTime::try_from(chrono::DateTime::<Local>::from(time).naive_local()).ok()
+}
+
+fn system_time(time: DateTime) -> Option<SystemTime> {
+    let local = NaiveDateTime::try_from(time).ok()?.and_local_timezone(Local).earliest()?;
+    Some(local.into())
+}
+
+/// Splits `files` into at most `threads` groups of roughly equal total size.
+fn balance(files: &[ProjectFile], threads: usize) -> Vec<Vec<&ProjectFile>> {
+    let mut by_size: Vec<_> = files.iter().collect();
+    by_size.sort_by_key(|f| std::cmp::Reverse(f.size));
+
+    let mut groups: Vec<(u64, Vec<&ProjectFile>)> = vec![(0, Vec::new()); threads.min(files.len())];
+    for file in by_size {
+        let lightest = groups.iter_mut().min_by_key(|(size, _)| *size).unwrap();
+        lightest.0 += file.size;
+        lightest.1.push(file);
+    }
+
+    groups.into_iter().map(|(_, group)| group).collect()
+}
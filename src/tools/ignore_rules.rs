@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::Path;
+use anyhow::Context;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use crate::utils::project_config::BackupProfile;
+
+pub const IGNORE_FILE: &str = ".rsgetignore";
+
+/// Built-in rules applied before `.rsgetignore`, so a project file can override them with `!pattern`.
+pub const DEFAULT_IGNORE_PATTERNS: [&str; 8] = [
+    "DerivedDataCache/",
+    "Intermediate/",
+    "Binaries/",
+    ".git/",
+    ".rsget/",
+    "*.pdb",
+    "*.bak",
+    "*.tmp",
+];
+
+/// Exclusion rules for a project: the built-in defaults, the project's `.rsgetignore` and the
+/// selected profile's excludes, with gitignore semantics (last matching pattern wins, `!` negates,
+/// leading `/` anchors to the project root, trailing `/` matches directories only).
+pub struct IgnoreRules {
+    matcher: Gitignore,
+    include: Option<Gitignore>,
+}
+
+impl IgnoreRules {
+    pub fn load(project_path: &Path, profile: &BackupProfile) -> anyhow::Result<Self> {
+        let mut builder = GitignoreBuilder::new(project_path);
+        for pattern in DEFAULT_IGNORE_PATTERNS {
+            builder.add_line(None, pattern)?;
+        }
+
+        let ignore_path = project_path.join(IGNORE_FILE);
+        if ignore_path.is_file() {
+            let content = fs::read_to_string(&ignore_path)
+                .with_context(|| format!("Failed to read {}", ignore_path.display()))?;
+            for (number, line) in content.lines().enumerate() {
+                builder
+                    .add_line(Some(ignore_path.clone()), line)
+                    .with_context(|| format!("{}:{}: invalid pattern '{}'", IGNORE_FILE, number + 1, line))?;
+            }
+        }
+
+        for pattern in &profile.exclude {
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid profile exclude pattern '{}'", pattern))?;
+        }
+
+        let include = if profile.include.is_empty() {
+            None
+        } else {
+            let mut include = GitignoreBuilder::new(project_path);
+            for pattern in &profile.include {
+                include
+                    .add_line(None, pattern)
+                    .with_context(|| format!("Invalid profile include pattern '{}'", pattern))?;
+            }
+            Some(include.build()?)
+        };
+
+        Ok(IgnoreRules {
+            matcher: builder.build()?,
+            include,
+        })
+    }
+
+    /// `relative_path` is relative to the project root; parents are expected to be checked first.
+    pub fn is_ignored(&self, relative_path: &Path, is_dir: bool) -> bool {
+        matches!(self.matcher.matched(relative_path, is_dir), Match::Ignore(_))
+    }
+
+    /// Whether a file that is not ignored belongs to the selected profile.
+    pub fn is_included(&self, relative_path: &Path) -> bool {
+        match &self.include {
+            Some(include) => include.matched_path_or_any_parents(relative_path, false).is_ignore(),
+            None => true,
+        }
+    }
+}
+
+/// Paths picked from a backup by globs such as `Content/Maps/**` or `Config/DefaultEngine.ini`,
+/// with the same syntax as profile include patterns. A pattern naming a directory selects everything in it.
+#[derive(Clone)]
+pub struct PathSelection {
+    matcher: Gitignore,
+}
+
+impl PathSelection {
+    pub fn new(patterns: &[String]) -> anyhow::Result<Self> {
+        let mut builder = GitignoreBuilder::new("");
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid path pattern '{}'", pattern))?;
+        }
+        Ok(PathSelection { matcher: builder.build()? })
+    }
+
+    /// `relative_path` is an archive entry name, relative to the project root with `/` separators.
+    pub fn matches(&self, relative_path: &str) -> bool {
+        self.matcher.matched_path_or_any_parents(relative_path, false).is_ignore()
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+use crate::tools::checksum;
+use crate::tools::chunk_store::{PackWriter, Storage};
+use crate::tools::compressing::{EntryMetadata, ProjectFile};
+use crate::tools::progress::Progress;
+use crate::utils::backup_meta::{BackupFile, BackupLink, BackupMeta};
+use crate::utils::local_state::{LocalSnapshot, TrackedFile};
+
+/// Project files compared with the manifest of the backup an incremental push builds on.
+pub struct ManifestDiff {
+    /// Manifest of the new backup, in the order of the project files.
+    pub files: Vec<BackupFile>,
+    /// Whether each file goes into the new archive: it's new, or its contents changed since `base`.
+    pub changed: Vec<bool>,
+}
+
+impl ManifestDiff {
+    pub fn changed_count(&self) -> usize {
+        self.changed.iter().filter(|&&changed| changed).count()
+    }
+}
+
+/// Builds the manifest of `files`, comparing it with `base`. A file whose size and mtime match its
+/// entry in `base`, or its entry with the same hash in the `local` snapshot, keeps the recorded
+/// hash without being read; any other file is hashed, and only counts as changed when the hash
+/// differs. Without a base every file is new.
+pub fn diff_manifest(files: &[ProjectFile], base: Option<&BackupMeta>, local: Option<&LocalSnapshot>) -> anyhow::Result<ManifestDiff> {
+    let previous: HashMap<&str, &BackupFile> = base
+        .map(|meta| meta.files.iter().filter(|f| !f.sha256.is_empty()).map(|f| (f.path.as_str(), f)).collect())
+        .unwrap_or_default();
+    let previous = Previous::new(previous, local);
+
+    let mtimes = files
+        .iter()
+        .map(|f| Ok(mtime_ns(fs::metadata(&f.path)?.modified()?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let to_hash = files.iter().zip(&mtimes).filter(|(f, m)| unmodified(&previous, f, **m).is_none()).map(|(f, _)| f.size).sum();
+    let progress = Progress::new("Hashing", Some(to_hash));
+    let mut manifest = Vec::with_capacity(files.len());
+    let mut changed = Vec::with_capacity(files.len());
+    for (file, mtime_ns) in files.iter().zip(mtimes) {
+        let sha256 = match unmodified(&previous, file, mtime_ns) {
+            Some(previous) => previous.sha256.clone(),
+            None => {
+                let source = fs::File::open(&file.path).with_context(|| format!("Failed to open {}", file.path.display()))?;
+                checksum::sha256_reader(&mut progress.reader(&file.relative_path, source))
+                    .with_context(|| format!("Failed to read {}", file.path.display()))?
+            }
+        };
+        let stored_in = match previous.get(&file.relative_path) {
+            Some(previous) if previous.sha256 == sha256 => previous.stored_in.clone().or_else(|| base.map(|b| b.id.clone())),
+            _ => None,
+        };
+        changed.push(stored_in.is_none());
+        manifest.push(BackupFile {
+            path: file.relative_path.clone(),
+            size: file.size,
+            mtime_ns,
+            sha256,
+            stored_in,
+            mode: None,
+            chunks: Vec::new(),
+        });
+    }
+    progress.finish("Hashing complete!");
+
+    Ok(ManifestDiff { files: manifest, changed })
+}
+
+/// Builds the manifest of a push to the chunk store. A file whose size and mtime match its entry
+/// in `base`, or in the `local` snapshot as for [`diff_manifest`], keeps the recorded chunks without
+/// being read; any other file is chunked through `packs`, which writes only the chunks the store
+/// doesn't have yet.
+pub fn chunk_manifest(
+    files: &[ProjectFile],
+    base: Option<&BackupMeta>,
+    local: Option<&LocalSnapshot>,
+    packs: &mut PackWriter,
+) -> anyhow::Result<Vec<BackupFile>> {
+    let previous: HashMap<&str, &BackupFile> = base
+        .filter(|meta| meta.storage == Storage::Chunks)
+        .map(|meta| meta.files.iter().map(|f| (f.path.as_str(), f)).collect())
+        .unwrap_or_default();
+    let previous = Previous::new(previous, local);
+
+    let metadata = files
+        .iter()
+        .map(|f| Ok(EntryMetadata::from_fs(&fs::metadata(&f.path)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mtimes: Vec<_> = metadata.iter().map(|m| m.modified.and_then(mtime_ns)).collect();
+
+    let to_chunk = files.iter().zip(&mtimes).filter(|(f, m)| unmodified(&previous, f, **m).is_none()).map(|(f, _)| f.size).sum();
+    let progress = Progress::new("Chunking", Some(to_chunk));
+    let mut manifest = Vec::with_capacity(files.len());
+    for ((file, metadata), mtime_ns) in files.iter().zip(metadata).zip(mtimes) {
+        let (chunks, sha256) = match unmodified(&previous, file, mtime_ns) {
+            Some(previous) => (previous.chunks.clone(), previous.sha256.clone()),
+            None => {
+                let source = fs::File::open(&file.path).with_context(|| format!("Failed to open {}", file.path.display()))?;
+                packs
+                    .add_file(&mut progress.reader(&file.relative_path, source))
+                    .with_context(|| format!("Failed to read {}", file.path.display()))?
+            }
+        };
+        manifest.push(BackupFile {
+            path: file.relative_path.clone(),
+            size: file.size,
+            mtime_ns,
+            sha256,
+            stored_in: None,
+            mode: metadata.mode,
+            chunks,
+        });
+    }
+    progress.finish("Chunking complete!");
+
+    Ok(manifest)
+}
+
+/// Hex SHA-256 of the tree a snapshot holds: the path, size and hash of every file, every empty
+/// directory and every link target. Snapshots of the same tree hash equally whatever their
+/// storage, format, timestamps or the backups their contents are kept in.
+pub fn tree_hash(files: &[BackupFile], empty_dirs: &[String], links: &[BackupLink]) -> String {
+    let mut entries: Vec<String> = files.iter().map(|f| format!("file\0{}\0{}\0{}", f.path, f.size, f.sha256)).collect();
+    entries.extend(empty_dirs.iter().map(|path| format!("dir\0{}", path)));
+    entries.extend(links.iter().map(|l| format!("link\0{}\0{}", l.path, l.target)));
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    for entry in entries {
+        hasher.update(entry.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Manifest of the base backup, with the snapshot last pushed or pulled on this machine. After a
+/// pull the files carry the mtimes they were restored with rather than the ones in the manifest,
+/// and the local snapshot is what knows them.
+struct Previous<'a> {
+    files: HashMap<&'a str, &'a BackupFile>,
+    local: HashMap<&'a str, &'a TrackedFile>,
+}
+
+impl<'a> Previous<'a> {
+    fn new(files: HashMap<&'a str, &'a BackupFile>, local: Option<&'a LocalSnapshot>) -> Self {
+        let local = local.map(|s| s.files.iter().map(|f| (f.path.as_str(), f)).collect()).unwrap_or_default();
+        Previous { files, local }
+    }
+
+    fn get(&self, path: &str) -> Option<&'a BackupFile> {
+        self.files.get(path).copied()
+    }
+}
+
+/// Entry of `file` in `previous` when its size and mtime show it hasn't been modified since.
+fn unmodified<'a>(previous: &Previous<'a>, file: &ProjectFile, mtime_ns: Option<u64>) -> Option<&'a BackupFile> {
+    let path = file.relative_path.as_str();
+    previous.get(path).filter(|p| {
+        (p.size == file.size && p.mtime_ns.is_some() && p.mtime_ns == mtime_ns)
+            || previous.local.get(path).is_some_and(|l| l.sha256 == p.sha256 && !l.sha256.is_empty() && l.matches(file.size, mtime_ns))
+    })
+}
+
+pub fn mtime_ns(modified: SystemTime) -> Option<u64> {
+    let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(since_epoch.as_nanos()).ok()
+}
@@ -1,2 +1,9 @@
 pub mod aws;
-pub mod compressing;
\ No newline at end of file
+pub mod chunk_store;
+pub mod checksum;
+pub mod compressing;
+pub mod ignore_rules;
+pub mod manifest;
+pub mod path_names;
+pub mod progress;
+pub mod remote_archive;
\ No newline at end of file
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Component, Path};
+use unicode_normalization::UnicodeNormalization;
+
+/// Characters Windows doesn't allow in file names, besides control characters. `\` is a
+/// separator there, so a Unix name containing it would be split into directories.
+const WINDOWS_RESERVED_CHARS: [char; 8] = ['<', '>', ':', '"', '|', '?', '*', '\\'];
+/// Device names Windows reserves whatever the extension, e.g. `aux.txt`.
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3",
+    "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+/// Problems listed in full by [`UnportablePaths`], the rest are counted.
+const MAX_LISTED: usize = 50;
+
+/// Why a backed-up path couldn't be restored on every platform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathProblem {
+    /// Names that are one file on a case-insensitive file system, or one name in different
+    /// Unicode normalization forms.
+    Conflict(Vec<String>),
+    Invalid { path: String, reason: &'static str },
+}
+
+impl fmt::Display for PathProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathProblem::Conflict(names) if names.windows(2).all(|pair| pair[0] == pair[1]) => {
+                write!(f, "{} ({} names that differ only in Unicode normalization)", names[0], names.len())
+            }
+            PathProblem::Conflict(names) => write!(f, "{}: differ only by case", names.join(", ")),
+            PathProblem::Invalid { path, reason } => write!(f, "{}: {}", path, reason),
+        }
+    }
+}
+
+/// Returned by [`PortableNames::check`]; push fails with it before anything is compressed.
+#[derive(Debug, Clone)]
+pub struct UnportablePaths {
+    pub problems: Vec<PathProblem>,
+}
+
+impl fmt::Display for UnportablePaths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} paths can't be restored on every platform, rename them or add them to .rsgetignore:", self.problems.len())?;
+        for problem in self.problems.iter().take(MAX_LISTED) {
+            write!(f, "\n  {}", problem)?;
+        }
+        if self.problems.len() > MAX_LISTED {
+            write!(f, "\n  ... and {} more", self.problems.len() - MAX_LISTED)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnportablePaths {}
+
+/// Entry names of a backup, checked for what would stop them from being restored on another OS:
+/// backups made on Windows are restored on Linux and the other way round.
+#[derive(Debug, Default)]
+pub struct PortableNames {
+    /// Names by their case-folded form.
+    folded: BTreeMap<String, Vec<String>>,
+    /// Spellings of every parent directory by its case-folded form. Directories aren't always
+    /// added themselves, and `Content/A/x` and `content/b/y` still land in one `Content` on Windows.
+    directories: BTreeMap<String, BTreeSet<String>>,
+    /// Reasons by the path up to the offending component, so a bad directory name is reported
+    /// once rather than for every file in it.
+    invalid: BTreeMap<String, &'static str>,
+}
+
+impl PortableNames {
+    /// Returns the archive entry name of `relative_path`: its components joined with `/` and in
+    /// Unicode NFC, since macOS hands out decomposed names. Anything that makes the name
+    /// unportable is recorded for [`PortableNames::check`].
+    pub fn add(&mut self, relative_path: &Path) -> String {
+        let mut parts = Vec::new();
+        for component in relative_path.components() {
+            let Component::Normal(part) = component else {
+                continue;
+            };
+            let (part, reason) = match part.to_str() {
+                Some(part) => {
+                    let part = part.nfc().collect::<String>();
+                    let reason = windows_problem(&part);
+                    (part, reason)
+                }
+                None => (part.to_string_lossy().nfc().collect(), Some("is not valid UTF-8")),
+            };
+            parts.push(part);
+            if let Some(reason) = reason {
+                self.invalid.entry(parts.join("/")).or_insert(reason);
+            }
+        }
+
+        for depth in 1..parts.len() {
+            let directory = parts[..depth].join("/");
+            self.directories.entry(directory.to_lowercase()).or_default().insert(directory);
+        }
+
+        let name = parts.join("/");
+        self.folded.entry(name.to_lowercase()).or_default().push(name.clone());
+        name
+    }
+
+    /// Fails with every problem found, conflicts first.
+    pub fn check(mut self) -> Result<(), UnportablePaths> {
+        for (folded, spellings) in self.directories {
+            let names = self.folded.entry(folded).or_default();
+            for spelling in spellings {
+                if !names.contains(&spelling) {
+                    names.push(spelling);
+                }
+            }
+        }
+
+        let mut problems: Vec<_> = self
+            .folded
+            .into_values()
+            .filter(|names| names.len() > 1)
+            .map(|mut names| {
+                names.sort();
+                PathProblem::Conflict(names)
+            })
+            .collect();
+        problems.extend(self.invalid.into_iter().map(|(path, reason)| PathProblem::Invalid { path, reason }));
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(UnportablePaths { problems }),
+        }
+    }
+}
+
+/// Why Windows refuses or mangles the single path component `part`.
+fn windows_problem(part: &str) -> Option<&'static str> {
+    if part.chars().any(|c| WINDOWS_RESERVED_CHARS.contains(&c)) {
+        return Some("contains one of < > : \" | ? * \\, which Windows doesn't allow");
+    }
+    if part.chars().any(char::is_control) {
+        return Some("contains control characters");
+    }
+    if part.ends_with(['.', ' ']) {
+        return Some("ends with a dot or a space, which Windows drops");
+    }
+    let stem = part.split('.').next().unwrap_or(part).trim_end();
+    if WINDOWS_RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        return Some("is a device name reserved on Windows");
+    }
+    None
+}
@@ -0,0 +1,153 @@
+use std::io::{self, IsTerminal, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// How often the plain-text reporter prints a status line.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Byte progress of a long-running stage: bytes done against the total, the current file and
+/// throughput. Draws a bar on a terminal; when output is redirected (CI, logs) it prints a status
+/// line every few seconds instead. Cheap to clone and safe to share between worker threads.
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+    /// Set when output isn't a terminal, holds the time of the last printed line.
+    plain: Option<Arc<Mutex<Instant>>>,
+    stage: &'static str,
+}
+
+impl Progress {
+    /// `total` is the number of bytes the stage processes, `None` when it isn't known up front.
+    pub fn new(stage: &'static str, total: Option<u64>) -> Self {
+        let interactive = io::stderr().is_terminal();
+        let bar = match total {
+            Some(total) => ProgressBar::new(total),
+            None => ProgressBar::no_length(),
+        };
+        if !interactive {
+            bar.set_draw_target(ProgressDrawTarget::hidden());
+        }
+
+        let template = match total {
+            Some(_) => "{spinner} {prefix} [{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta}) {wide_msg}",
+            None => "{spinner} {prefix} {bytes} ({bytes_per_sec}) {wide_msg}",
+        };
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(template)
+                .expect("valid progress template")
+                .progress_chars("=> "),
+        );
+        bar.set_prefix(stage);
+        if interactive {
+            bar.enable_steady_tick(Duration::from_millis(100));
+        }
+
+        Progress {
+            bar,
+            plain: (!interactive).then(|| Arc::new(Mutex::new(Instant::now()))),
+            stage,
+        }
+    }
+
+    /// Shows `name` as the file being processed.
+    pub fn set_file(&self, name: &str) {
+        self.bar.set_message(name.to_string());
+    }
+
+    pub fn inc(&self, bytes: u64) {
+        self.bar.inc(bytes);
+        if let Some(last) = &self.plain {
+            let mut last = last.lock().unwrap();
+            if last.elapsed() >= PLAIN_INTERVAL {
+                *last = Instant::now();
+                println!("{}", self.status());
+            }
+        }
+    }
+
+    /// Bytes processed so far.
+    pub fn position(&self) -> u64 {
+        self.bar.position()
+    }
+
+    /// Prints `line` above the bar without breaking it.
+    pub fn println(&self, line: impl AsRef<str>) {
+        if self.plain.is_some() {
+            println!("{}", line.as_ref());
+        } else {
+            self.bar.println(line.as_ref());
+        }
+    }
+
+    /// Stops the bar and leaves a summary line with the bytes processed, elapsed time and throughput.
+    pub fn finish(&self, message: &str) {
+        let elapsed = self.bar.elapsed();
+        let bytes = self.bar.position();
+        let summary = format!(
+            "{} ({} in {}, {}/s)",
+            message,
+            HumanBytes(bytes),
+            HumanDuration(elapsed),
+            HumanBytes((bytes as f64 / elapsed.as_secs_f64().max(0.001)) as u64)
+        );
+        if self.plain.is_some() {
+            println!("{}", summary);
+        } else {
+            self.bar.set_style(ProgressStyle::default_bar().template("{msg}").expect("valid progress template"));
+            self.bar.finish_with_message(summary);
+        }
+    }
+
+    /// Removes the bar without a summary, e.g. when the stage failed.
+    pub fn abandon(&self) {
+        self.bar.finish_and_clear();
+    }
+
+    /// Wraps `inner` so every byte read from it advances the progress, showing `name`
+    /// as the current file once reading starts.
+    pub fn reader<R: Read>(&self, name: &str, inner: R) -> ProgressReader<R> {
+        ProgressReader {
+            progress: self.clone(),
+            name: Some(name.to_string()),
+            inner,
+        }
+    }
+
+    fn status(&self) -> String {
+        let bytes = self.bar.position();
+        let per_sec = HumanBytes(self.bar.per_sec() as u64);
+        let file = self.bar.message();
+        match self.bar.length().filter(|&total| total > 0) {
+            Some(total) => format!(
+                "{}: {}% ({}/{}), {}/s {}",
+                self.stage,
+                bytes * 100 / total,
+                HumanBytes(bytes),
+                HumanBytes(total),
+                per_sec,
+                file
+            ),
+            None => format!("{}: {}, {}/s {}", self.stage, HumanBytes(bytes), per_sec, file),
+        }
+    }
+}
+
+/// See [`Progress::reader`].
+pub struct ProgressReader<R> {
+    progress: Progress,
+    name: Option<String>,
+    inner: R,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(name) = self.name.take() {
+            self.progress.set_file(&name);
+        }
+        let n = self.inner.read(buf)?;
+        self.progress.inc(n as u64);
+        Ok(n)
+    }
+}
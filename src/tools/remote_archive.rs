@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom};
+use aws_sdk_s3::Client;
+use tokio::runtime::Handle;
+use crate::tools;
+use crate::tools::progress::Progress;
+use crate::utils::backup_meta::BackupMeta;
+
+/// Blocks kept in memory, so the back-and-forth seeks of a header parser don't refetch them.
+const CACHED_BLOCKS: usize = 8;
+
+/// Read-only view of a remote archive that fetches only the blocks being read with ranged GETs.
+/// A split archive reads as one stream across its volumes. Reads block on the runtime the view
+/// was created on, so it must be used from a blocking thread, e.g. inside `spawn_blocking`.
+pub struct RemoteArchive {
+    client: Client,
+    bucket: String,
+    /// Objects the archive consists of in order, with their sizes.
+    parts: Vec<(String, u64)>,
+    len: u64,
+    position: u64,
+    block_size: u64,
+    cache: VecDeque<(u64, Vec<u8>)>,
+    fetched: u64,
+    /// Counts the bytes fetched; retries are printed above it.
+    progress: Progress,
+    runtime: Handle,
+}
+
+impl RemoteArchive {
+    /// `block_size` is the unit of every GET: small for reading an index, large for streaming.
+    pub fn new(client: &Client, bucket: &str, meta: &BackupMeta, block_size: u64, progress: &Progress) -> Self {
+        let parts: Vec<_> = if meta.volumes.is_empty() {
+            vec![(meta.object_key.clone(), meta.archive_size)]
+        } else {
+            meta.volumes.iter().map(|v| (v.key.clone(), v.size)).collect()
+        };
+        RemoteArchive {
+            client: client.clone(),
+            bucket: bucket.to_string(),
+            len: parts.iter().map(|(_, size)| size).sum(),
+            parts,
+            position: 0,
+            block_size,
+            cache: VecDeque::new(),
+            fetched: 0,
+            progress: progress.clone(),
+            runtime: Handle::current(),
+        }
+    }
+
+    /// Bytes downloaded so far.
+    pub fn fetched(&self) -> u64 {
+        self.fetched
+    }
+
+    fn block(&mut self, index: u64) -> io::Result<&[u8]> {
+        if let Some(cached) = self.cache.iter().position(|(i, _)| *i == index) {
+            return Ok(&self.cache[cached].1);
+        }
+
+        let start = index * self.block_size;
+        let end = (start + self.block_size).min(self.len);
+        let mut data = Vec::with_capacity((end - start) as usize);
+        let mut part_start = 0;
+        for (key, size) in &self.parts {
+            let part_end = part_start + size;
+            if part_end > start && part_start < end {
+                let from = start.max(part_start);
+                let to = end.min(part_end);
+                let progress = &self.progress;
+                let range = tools::aws::get_range(&self.client, &self.bucket, key, from - part_start, to - from, |message| progress.println(message));
+                data.extend(self.runtime.block_on(range).map_err(io::Error::other)?);
+            }
+            part_start = part_end;
+        }
+        self.fetched += data.len() as u64;
+        self.progress.inc(data.len() as u64);
+
+        if self.cache.len() == CACHED_BLOCKS {
+            self.cache.pop_front();
+        }
+        self.cache.push_back((index, data));
+        Ok(&self.cache.back().unwrap().1)
+    }
+}
+
+impl Read for RemoteArchive {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let (index, offset) = (self.position / self.block_size, (self.position % self.block_size) as usize);
+        let block = self.block(index)?;
+        let n = buf.len().min(block.len() - offset);
+        buf[..n].copy_from_slice(&block[offset..offset + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for RemoteArchive {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "Seek before the start of the archive")),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+/// REPL command arguments split into `--flag`s, `--option value` pairs and positional values.
+pub struct CommandArgs<'a> {
+    flags: Vec<&'a str>,
+    options: Vec<(&'a str, &'a str)>,
+    pub positional: Vec<&'a str>,
+}
+
+impl<'a> CommandArgs<'a> {
+    /// `flags` and `options` list the accepted names including the leading `--`.
+    pub fn parse(args: &[&'a str], flags: &[&str], options: &[&str]) -> Result<Self, String> {
+        let mut parsed = CommandArgs {
+            flags: Vec::new(),
+            options: Vec::new(),
+            positional: Vec::new(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(&arg) = iter.next() {
+            if flags.contains(&arg) {
+                parsed.flags.push(arg);
+            } else if options.contains(&arg) {
+                let value = iter.next().ok_or_else(|| format!("Missing value for {}", arg))?;
+                parsed.options.push((arg, value));
+            } else if arg.starts_with("--") {
+                return Err(format!("Unknown option: {}", arg));
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(&name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&'a str> {
+        self.options.iter().rev().find(|(option, _)| *option == name).map(|(_, value)| *value)
+    }
+}
+
+/// Splits a REPL line into words on whitespace. Double or single quotes keep a value with spaces
+/// in one word, e.g. `push --message "Rework the main map"`. Backslashes are kept as is so Windows
+/// paths need no escaping.
+pub fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+
+    for c in line.chars() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => word.get_or_insert_with(String::new).push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => words.extend(word.take()),
+            None => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(open) = quote {
+        return Err(format!("Unterminated {} quote", open));
+    }
+    words.extend(word);
+    Ok(words)
+}
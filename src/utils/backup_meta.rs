@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use crate::tools::chunk_store::Storage;
+use crate::tools::compressing::{ArchiveFormat, SymlinkPolicy};
+use crate::utils::password::PasswordCheck;
+
+/// `chrono` format of snapshot ids: the UTC time of the push, so ids sort in push order.
+pub const SNAPSHOT_ID_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Snapshot of a project, stored next to its backup archive as `<backups_prefix>/<id>.json`, so a
+/// backup can be inspected without downloading the archive itself. Snapshots are immutable: a push
+/// never replaces an existing one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupMeta {
+    pub id: String,
+    pub project_name: String,
+    /// Archive of the backup; for a backup in the chunk store, its metadata object.
+    pub object_key: String,
+    /// Size of the archive; for a backup in the chunk store, of every chunk it references.
+    pub archive_size: u64,
+    /// Archive format of `object_key`; backups made before formats were selectable are 7z.
+    #[serde(default)]
+    pub format: ArchiveFormat,
+    /// Hex SHA-256 of the archive, checked after every download.
+    #[serde(default)]
+    pub sha256: String,
+    pub created_by: String,
+    pub created_at: String,
+    /// Snapshot that was the latest of the project when this one was pushed; `None` for the first
+    /// snapshot and for backups made before snapshots had parents.
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// Message given with `push --message`.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// See [`tree_hash`](crate::tools::manifest::tree_hash); empty for backups made before snapshots.
+    #[serde(default)]
+    pub tree_hash: String,
+    /// Profile the backup was pushed with; `None` for a whole-project backup.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Include patterns of that profile, empty when the backup is not limited to a subset.
+    #[serde(default)]
+    pub profile_include: Vec<String>,
+    /// Symlink policy the backup was pushed with; `None` for backups made before it was recorded.
+    #[serde(default)]
+    pub symlinks: Option<SymlinkPolicy>,
+    /// Parts of a split archive in order, each stored as its own object; empty when the
+    /// archive is the single object at `object_key`.
+    #[serde(default)]
+    pub volumes: Vec<BackupVolume>,
+    /// Whether the archive is encrypted, so pull asks for the password before downloading it.
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Check of the password an encrypted backup was pushed with; `None` when it is not encrypted
+    /// or was pushed before the check was recorded.
+    #[serde(default)]
+    pub password_check: Option<PasswordCheck>,
+    /// Backup an incremental push was compared with; `None` for a full backup.
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Where the file contents are; backups made before the chunk store are archives.
+    #[serde(default)]
+    pub storage: Storage,
+    /// Manifest of every file of the backup, including the ones whose contents are kept in the
+    /// archive of an earlier backup, see [`BackupFile::stored_in`].
+    pub files: Vec<BackupFile>,
+    /// Empty directories and links of a backup in the chunk store, which has no archive to keep
+    /// them as entries.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub empty_dirs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<BackupLink>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupVolume {
+    pub key: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupFile {
+    pub path: String,
+    pub size: u64,
+    /// Modification time in nanoseconds since the Unix epoch; with `size` it tells whether the
+    /// file has to be hashed again on the next push.
+    #[serde(default)]
+    pub mtime_ns: Option<u64>,
+    /// Hex SHA-256 of the contents; empty in backups made before pushes were incremental.
+    #[serde(default)]
+    pub sha256: String,
+    /// Id of the earlier backup whose archive holds the contents, when the file hasn't changed
+    /// since; `None` when they are in this backup's archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stored_in: Option<String>,
+    /// Unix permission bits, recorded for backups in the chunk store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Chunks the contents are rebuilt from in order, for backups in the chunk store.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<ChunkRef>,
+}
+
+/// Piece of a file in the bucket's chunk store, named by the hex SHA-256 of its contents.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChunkRef {
+    pub id: String,
+    /// Pack object holding the chunk, see [`pack_key`](crate::tools::chunk_store::pack_key).
+    pub pack: String,
+    /// Position and length of the compressed chunk in the pack.
+    pub offset: u64,
+    pub length: u64,
+    /// Uncompressed size.
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupLink {
+    pub path: String,
+    pub target: String,
+}
+
+impl BackupMeta {
+    /// Whether `id` has the form of a snapshot id, e.g. `20250101T120000Z`.
+    pub fn is_id(id: &str) -> bool {
+        NaiveDateTime::parse_from_str(id, SNAPSHOT_ID_FORMAT).is_ok()
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    /// Files whose contents are in this backup's own archive.
+    pub fn archived_files(&self) -> impl Iterator<Item = &BackupFile> {
+        self.files.iter().filter(|f| f.stored_in.is_none())
+    }
+
+    /// Size of [`Self::archived_files`], what verifying this backup reads. Chunked backups keep
+    /// no base, so this is their whole size.
+    pub fn archived_size(&self) -> u64 {
+        self.archived_files().map(|f| f.size).sum()
+    }
+
+    /// Distinct chunks the files are rebuilt from, each counted once.
+    pub fn unique_chunks(&self) -> impl Iterator<Item = &ChunkRef> {
+        let mut seen = HashSet::new();
+        self.files.iter().flat_map(|f| &f.chunks).filter(move |c| seen.insert(c.id.as_str()))
+    }
+}
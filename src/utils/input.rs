@@ -15,7 +15,7 @@ pub struct MyHelper {
 }
 
 impl MyHelper {
-    pub(crate) fn new(commands: Vec<String>) -> Self {
+    pub fn new(commands: Vec<String>) -> Self {
         MyHelper {
             commands: commands.iter().map(|s| s.to_string()).collect(),
         }
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use crate::tools::compressing::SymlinkPolicy;
+use crate::tools::manifest::mtime_ns;
+use crate::utils::backup_meta::{BackupMeta, ChunkRef};
+use crate::utils::project_config::CONFIG_DIR;
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const HASH_CACHE_FILE: &str = "hash_cache.json";
+const PACK_INDEX_CACHE_FILE: &str = "pack_index_cache.json";
+
+/// Snapshot the working tree was last pushed as or pulled from on this machine, stored in
+/// `.rsget/snapshot.json` so `status` compares the tree without contacting the bucket.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LocalSnapshot {
+    pub id: String,
+    pub created_at: String,
+    /// `push` or `pull`.
+    pub synced_by: String,
+    /// Profile of the snapshot; `status` only looks at the files it covers.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Symlink policy of the snapshot, so `status` walks the tree the way the push did; `None`
+    /// for snapshots recorded before it was, which use the project's policy.
+    #[serde(default)]
+    pub symlinks: Option<SymlinkPolicy>,
+    pub files: Vec<TrackedFile>,
+}
+
+/// Size, mtime and hash of a file, as recorded in a snapshot manifest or taken by `status`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrackedFile {
+    pub path: String,
+    pub size: u64,
+    #[serde(default)]
+    pub mtime_ns: Option<u64>,
+    /// Hex SHA-256; empty for snapshots made before manifests had hashes.
+    #[serde(default)]
+    pub sha256: String,
+}
+
+impl TrackedFile {
+    /// Whether the file at `size` and `mtime_ns` is still the one this entry describes.
+    pub fn matches(&self, size: u64, mtime_ns: Option<u64>) -> bool {
+        self.size == size && self.mtime_ns.is_some() && self.mtime_ns == mtime_ns
+    }
+}
+
+impl LocalSnapshot {
+    pub fn from_meta(meta: &BackupMeta, synced_by: &str) -> Self {
+        LocalSnapshot {
+            id: meta.id.clone(),
+            created_at: meta.created_at.clone(),
+            synced_by: synced_by.to_string(),
+            profile: meta.profile.clone(),
+            symlinks: meta.symlinks,
+            files: meta
+                .files
+                .iter()
+                .map(|f| TrackedFile { path: f.path.clone(), size: f.size, mtime_ns: f.mtime_ns, sha256: f.sha256.clone() })
+                .collect(),
+        }
+    }
+
+    /// Snapshot `meta` just restored to `project_dir`. Restored files carry mtimes truncated to what
+    /// the archive format and the filesystem keep, so they are read back from disk rather than
+    /// taken from the manifest; otherwise every file would look touched to `status` and `push`.
+    pub fn restored(meta: &BackupMeta, project_dir: &Path) -> Self {
+        let mut snapshot = Self::from_meta(meta, "pull");
+        for file in &mut snapshot.files {
+            file.mtime_ns = fs::metadata(project_dir.join(&file.path))
+                .ok()
+                .filter(|m| m.len() == file.size)
+                .and_then(|m| m.modified().ok())
+                .and_then(mtime_ns);
+        }
+        snapshot
+    }
+
+    /// `None` when nothing was pushed or pulled on this machine yet.
+    pub fn load(project_dir: &Path) -> anyhow::Result<Option<Self>> {
+        load_json(&state_path(project_dir, SNAPSHOT_FILE))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> anyhow::Result<()> {
+        save_json(project_dir, SNAPSHOT_FILE, self)
+    }
+}
+
+/// Hashes of working tree files taken by `status`, stored in `.rsget/hash_cache.json`. A file whose
+/// size and mtime still match its entry isn't read again.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HashCache {
+    pub files: Vec<TrackedFile>,
+}
+
+impl HashCache {
+    /// An empty cache when there is none yet or it can't be read, since it only saves time.
+    pub fn load(project_dir: &Path) -> Self {
+        load_json(&state_path(project_dir, HASH_CACHE_FILE)).ok().flatten().unwrap_or_default()
+    }
+
+    pub fn save(&self, project_dir: &Path) -> anyhow::Result<()> {
+        save_json(project_dir, HASH_CACHE_FILE, self)
+    }
+}
+
+/// Pack indexes of the bucket's chunk store already read or written by a push from this project,
+/// stored in `.rsget/pack_index_cache.json`, so a push only downloads the indexes of packs added
+/// since. Packs are never rewritten, so a cached index stays valid as long as its pack is listed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PackIndexCache {
+    pub bucket: String,
+    /// Chunks of every cached pack by pack id.
+    pub packs: HashMap<String, Vec<ChunkRef>>,
+}
+
+impl PackIndexCache {
+    /// An empty cache when there is none for `bucket` yet or it can't be read, since it only saves time.
+    pub fn load(project_dir: &Path, bucket: &str) -> Self {
+        let cache: Self = load_json(&state_path(project_dir, PACK_INDEX_CACHE_FILE)).ok().flatten().unwrap_or_default();
+        if cache.bucket == bucket {
+            cache
+        } else {
+            PackIndexCache { bucket: bucket.to_string(), packs: HashMap::new() }
+        }
+    }
+
+    pub fn save(&self, project_dir: &Path) -> anyhow::Result<()> {
+        save_json(project_dir, PACK_INDEX_CACHE_FILE, self)
+    }
+}
+
+fn state_path(project_dir: &Path, file: &str) -> PathBuf {
+    project_dir.join(CONFIG_DIR).join(file)
+}
+
+fn load_json<T: for<'de> Deserialize<'de>>(path: &Path) -> anyhow::Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let value = serde_json::from_str(&data).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(value))
+}
+
+fn save_json<T: Serialize>(project_dir: &Path, file: &str, value: &T) -> anyhow::Result<()> {
+    let path = state_path(project_dir, file);
+    fs::create_dir_all(project_dir.join(CONFIG_DIR)).context("Failed to create .rsget directory")?;
+    fs::write(&path, serde_json::to_vec(value)?).with_context(|| format!("Failed to write {}", path.display()))
+}
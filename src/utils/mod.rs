@@ -1,6 +1,10 @@
 pub mod prints;
 pub mod router;
 pub mod project;
+pub mod project_config;
+pub mod local_state;
+pub mod backup_meta;
 pub mod unreal_engine;
 pub mod user;
-pub mod input;
\ No newline at end of file
+pub mod input;
+pub mod args;pub mod password;
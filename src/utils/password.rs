@@ -0,0 +1,74 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{bail, Context};
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Environment variable with the archive password, for scripts and CI where nobody can type it.
+pub const PASSWORD_ENV: &str = "RSGET_ARCHIVE_PASSWORD";
+
+/// Reads the archive password from [`PASSWORD_ENV`], or asks for it without echoing.
+/// With `confirm` the password has to be typed twice, so a typo doesn't lock the backup.
+pub fn read_password(confirm: bool) -> anyhow::Result<String> {
+    dotenv().ok();
+    if let Ok(password) = env::var(PASSWORD_ENV) {
+        if password.is_empty() {
+            bail!("{} is set but empty", PASSWORD_ENV);
+        }
+        return Ok(password);
+    }
+
+    let password = rpassword::prompt_password("Archive password: ").context("Failed to read the password")?;
+    if password.is_empty() {
+        bail!("Archive password must not be empty");
+    }
+    if confirm {
+        let repeated = rpassword::prompt_password("Repeat password: ").context("Failed to read the password")?;
+        if repeated != password {
+            bail!("Passwords do not match");
+        }
+    }
+    Ok(password)
+}
+
+/// PBKDF2 rounds of a new [`PasswordCheck`]. Each round is two SHA-256 compressions, so this is
+/// more work per guess than the 2^19 SHA-256 rounds 7z derives its key with, and the check is no
+/// cheaper to brute-force than a 7z archive. Zip AES derives its key with 1000 rounds of
+/// PBKDF2-HMAC-SHA1, so a zip archive stays the cheaper target whatever this is.
+const CHECK_ROUNDS: u32 = 600_000;
+
+/// Rounds of the checks written before the count was recorded.
+fn legacy_rounds() -> u32 {
+    100_000
+}
+
+/// Salted PBKDF2-HMAC-SHA256 of the password of an encrypted backup, stored in its metadata, so
+/// a push can tell whether it was given the same password without downloading the archive.
+/// The metadata isn't encrypted, so anyone who can read the bucket can run guesses against it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PasswordCheck {
+    pub salt: String,
+    pub hash: String,
+    #[serde(default = "legacy_rounds")]
+    pub rounds: u32,
+}
+
+impl PasswordCheck {
+    pub fn new(password: &str) -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+        let salt = hex::encode(&Sha256::digest(format!("{}:{}", nanos, std::process::id()))[..16]);
+        let hash = Self::derive(password, &salt, CHECK_ROUNDS);
+        PasswordCheck { salt, hash, rounds: CHECK_ROUNDS }
+    }
+
+    pub fn matches(&self, password: &str) -> bool {
+        Self::derive(password, &self.salt, self.rounds) == self.hash
+    }
+
+    fn derive(password: &str, salt: &str, rounds: u32) -> String {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), rounds, &mut key);
+        hex::encode(key)
+    }
+}
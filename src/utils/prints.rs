@@ -1,4 +1,16 @@
 use colored::*;
+use indicatif::{HumanBytes, HumanDuration};
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use chrono::{DateTime, Local};
+use crate::functions::list::BackupListing;
+use crate::functions::pull::RestorePlan;
+use crate::functions::push::{BackupPlan, TransferSummary};
+use crate::functions::status::{ChangeKind, StatusReport};
+use crate::functions::verify::VerifySummary;
+use crate::tools::chunk_store::Storage;
+use crate::tools::compressing::{ArchiveEntryInfo, OverwritePolicy};
+use crate::utils::backup_meta::BackupMeta;
 use crate::utils::project::Project;
 use crate::utils::unreal_engine::Engine;
 
@@ -107,19 +119,412 @@ impl Prints {
         println!("{}", "━".repeat(60).truecolor(100, 100, 100));
     }
 
+    pub fn print_transfer_summary(operation: &str, summary: &TransferSummary) {
+        println!("\n{}", format!("✅ {} complete", operation).bright_green().bold());
+        println!("   {} {}", "Object:  ".bright_cyan(), summary.object_key);
+        println!("   {} {}", "Size:    ".bright_cyan(), HumanBytes(summary.bytes));
+        println!("   {} {}", "Duration:".bright_cyan(), HumanDuration(summary.duration));
+
+        if let Some(report) = &summary.compression {
+            println!(
+                "   {} {}, {} level {}, {} threads",
+                "Format:  ".bright_cyan(),
+                report.format,
+                report.method,
+                report.level,
+                report.threads
+            );
+            if report.input_bytes > 0 {
+                println!(
+                    "   {} {} -> {} ({:.1}%)",
+                    "Ratio:   ".bright_cyan(),
+                    HumanBytes(report.input_bytes),
+                    HumanBytes(summary.bytes),
+                    summary.bytes as f64 * 100.0 / report.input_bytes as f64
+                );
+            }
+            if report.encrypted {
+                println!("   {} AES-256", "Encrypted:".bright_cyan());
+            }
+            if report.stored_files > 0 {
+                println!(
+                    "   {} {} files ({}) without compression",
+                    "Stored:  ".bright_cyan(),
+                    report.stored_files,
+                    HumanBytes(report.stored_bytes)
+                );
+            }
+        }
+
+        if let Some(report) = &summary.chunks {
+            println!(
+                "   {} {} of {} new ({}) in {} packs",
+                "Chunks:  ".bright_cyan(),
+                report.new_chunks,
+                report.chunks,
+                HumanBytes(report.new_bytes),
+                report.packs
+            );
+            println!("   {} {} files ({})", "Read:    ".bright_cyan(), report.chunked_files, HumanBytes(report.chunked_bytes));
+        }
+
+        if let Some(report) = &summary.extraction {
+            println!("   {} {}", "Restored:".bright_cyan(), report.extracted);
+            if report.kept > 0 {
+                println!("   {} {} local files kept", "Kept:    ".bright_cyan(), report.kept);
+            }
+            if report.renamed > 0 {
+                println!("   {} {} files restored next to local ones as 'name (1).ext'", "Renamed: ".bright_cyan(), report.renamed);
+            }
+            if let Some(trash_dir) = &report.trash_dir {
+                println!("   {} {} replaced files moved to {}", "Trash:   ".bright_cyan(), report.trashed, trash_dir.display());
+            }
+            if report.linked > 0 {
+                println!("   {} {} links recreated", "Links:   ".bright_cyan(), report.linked);
+            }
+            for warning in &report.link_warnings {
+                println!("   {} {}", "Warning: ".yellow(), warning);
+            }
+        }
+    }
+
+    pub fn print_verify_summary(summary: &VerifySummary) {
+        const MAX_LISTED: usize = 20;
+
+        let report = &summary.report;
+        if summary.is_intact() {
+            println!("\n{}", "✅ Backup is intact".bright_green().bold());
+        } else {
+            println!("\n{}", "❌ Backup is damaged".red().bold());
+        }
+        println!("   {} {}", "Object:  ".bright_cyan(), summary.object_key);
+        for base in &summary.bases {
+            println!("   {} {}", "Base:    ".bright_cyan(), base);
+        }
+        println!("   {} {} files, {}", "Decoded: ".bright_cyan(), report.verified.len(), HumanBytes(report.bytes));
+        println!("   {} {}", "Duration:".bright_cyan(), HumanDuration(summary.duration));
+
+        for mismatch in &summary.mismatches {
+            println!("   {} {}", "➤".red(), mismatch);
+        }
+        if !report.corrupt.is_empty() {
+            println!("{}", format!("{} corrupt entries:", report.corrupt.len()).red().bold());
+            for entry in report.corrupt.iter().take(MAX_LISTED) {
+                println!("   {} {}: {}", "➤".red(), entry.name.as_deref().unwrap_or("archive structure"), entry.error);
+            }
+            if report.corrupt.len() > MAX_LISTED {
+                println!("   ... and {} more", report.corrupt.len() - MAX_LISTED);
+            }
+        }
+        if !summary.missing.is_empty() {
+            let stopped = report.corrupt.iter().any(|c| c.name.is_none());
+            let message = if stopped {
+                format!("{} files could not be checked past the damage:", summary.missing.len())
+            } else {
+                format!("{} files listed in the backup are missing from the archive:", summary.missing.len())
+            };
+            println!("{}", message.red().bold());
+            for file in summary.missing.iter().take(MAX_LISTED) {
+                println!("   {} {}", "➤".red(), file);
+            }
+            if summary.missing.len() > MAX_LISTED {
+                println!("   ... and {} more", summary.missing.len() - MAX_LISTED);
+            }
+        }
+    }
+
+    pub fn print_backup_listing(listing: &BackupListing, tree: bool) {
+        let files = listing.entries.iter().filter(|e| !e.is_dir).count();
+        let size: u64 = listing.entries.iter().map(|e| e.size).sum();
+        println!("\n{} {}", "📦 Backup:".bright_cyan().bold(), listing.object_key);
+        println!(
+            "   {} files, {} unpacked; read {} of the {} archive",
+            files,
+            HumanBytes(size),
+            HumanBytes(listing.fetched),
+            HumanBytes(listing.archive_size)
+        );
+        if listing.entries.is_empty() {
+            println!("{}", "No matching entries".yellow());
+            return;
+        }
+
+        if tree {
+            Self::print_entry_tree(&listing.entries);
+        } else {
+            for entry in &listing.entries {
+                let size = if entry.is_dir { "-".to_string() } else { HumanBytes(entry.size).to_string() };
+                let path = match (entry.is_dir, entry.is_link) {
+                    (true, _) => format!("{}/", entry.path),
+                    (_, true) => format!("{}@", entry.path),
+                    _ => entry.path.clone(),
+                };
+                println!("   {:<16}  {:>10}  {}", modified_time(entry.modified), size, path);
+            }
+        }
+    }
+
+    fn print_entry_tree(entries: &[ArchiveEntryInfo]) {
+        #[derive(Default)]
+        struct Node<'a> {
+            entry: Option<&'a ArchiveEntryInfo>,
+            children: BTreeMap<&'a str, Node<'a>>,
+        }
+
+        fn print_node(node: &Node, prefix: &str) {
+            // Directories first, each group sorted by name
+            let mut children: Vec<_> = node.children.iter().collect();
+            children.sort_by_key(|(name, child)| (child.entry.is_some_and(|e| !e.is_dir), *name));
+            for (i, (name, child)) in children.iter().enumerate() {
+                let last = i + 1 == children.len();
+                let branch = if last { "└── " } else { "├── " };
+                match child.entry.filter(|e| !e.is_dir) {
+                    Some(entry) => println!(
+                        "   {}{}{} {}",
+                        prefix,
+                        branch,
+                        name,
+                        format!("({}, {})", HumanBytes(entry.size), modified_time(entry.modified)).bright_black()
+                    ),
+                    None => println!("   {}{}{}", prefix, branch, format!("{}/", name).bright_cyan()),
+                }
+                print_node(child, &format!("{}{}", prefix, if last { "    " } else { "│   " }));
+            }
+        }
+
+        let mut root = Node::default();
+        for entry in entries {
+            let mut node = &mut root;
+            for component in entry.path.split('/') {
+                node = node.children.entry(component).or_default();
+            }
+            node.entry = Some(entry);
+        }
+        print_node(&root, "");
+    }
+
+    pub fn print_status(report: &StatusReport) {
+        println!(
+            "\n{} {} ({} at {})",
+            "📍 Snapshot:".bright_cyan().bold(),
+            report.snapshot.id,
+            report.snapshot.synced_by,
+            report.snapshot.created_at
+        );
+        if let Some(profile) = &report.snapshot.profile {
+            println!("   {} '{}', files outside it are not compared", "Profile:".bright_cyan(), profile);
+        }
+        if report.changes.is_empty() {
+            println!("{}", format!("No changes, {} files match the snapshot", report.unchanged).green());
+            return;
+        }
+
+        println!(
+            "   {} added, {} modified, {} deleted, {} unchanged",
+            report.count(ChangeKind::Added),
+            report.count(ChangeKind::Modified),
+            report.count(ChangeKind::Deleted),
+            report.unchanged
+        );
+        for change in &report.changes {
+            let size = |size: Option<u64>| HumanBytes(size.unwrap_or_default()).to_string();
+            match change.kind {
+                ChangeKind::Added => println!("   {} {} ({})", "+".green(), change.path.green(), size(change.size)),
+                ChangeKind::Modified if change.size == change.snapshot_size => {
+                    println!("   {} {} ({})", "~".yellow(), change.path.yellow(), size(change.size))
+                }
+                ChangeKind::Modified => {
+                    println!("   {} {} ({} -> {})", "~".yellow(), change.path.yellow(), size(change.snapshot_size), size(change.size))
+                }
+                ChangeKind::Deleted => println!("   {} {} ({})", "-".red(), change.path.red(), size(change.snapshot_size)),
+            }
+        }
+    }
+
+    pub fn print_snapshot_log(snapshots: &[BackupMeta]) {
+        if snapshots.is_empty() {
+            println!("{}", "No snapshots yet, use 'push' to create one".yellow());
+            return;
+        }
+
+        for (index, snapshot) in snapshots.iter().enumerate() {
+            let latest = if index == 0 { " (latest)".green().bold().to_string() } else { String::new() };
+            println!("\n{} {}{}", "●".bright_cyan().bold(), snapshot.id.bright_yellow(), latest);
+            println!("   {} {} at {}", "Author:".bright_cyan(), snapshot.created_by, snapshot.created_at);
+            if let Some(parent) = &snapshot.parent {
+                println!("   {} {}", "Parent:".bright_cyan(), parent);
+            }
+            if !snapshot.tree_hash.is_empty() {
+                println!("   {} {}", "Tree:  ".bright_cyan(), snapshot.tree_hash.get(..12).unwrap_or(&snapshot.tree_hash));
+            }
+            let storage = match snapshot.storage {
+                Storage::Archive => snapshot.format.to_string(),
+                Storage::Chunks => snapshot.storage.to_string(),
+            };
+            println!(
+                "   {} {} files, {} ({}{})",
+                "Files: ".bright_cyan(),
+                snapshot.files.len(),
+                HumanBytes(snapshot.total_size()),
+                storage,
+                snapshot.profile.as_ref().map(|p| format!(", profile '{}'", p)).unwrap_or_default()
+            );
+            if let Some(message) = &snapshot.message {
+                println!("\n       {}", message);
+            }
+        }
+    }
+
+    pub fn print_backup_plan(plan: &BackupPlan) {
+        const MAX_LISTED: usize = 10;
+
+        println!("\n{}", "🔎 Dry run: nothing will be compressed or uploaded".bright_cyan().bold());
+        println!("   {} {}", "Target:".bright_cyan(), plan.object_key);
+        match plan.storage {
+            Storage::Archive => println!("   {} {}", "Format:".bright_cyan(), plan.format),
+            Storage::Chunks => println!("   {} {}", "Storage:".bright_cyan(), plan.storage),
+        }
+        if let Some(profile) = &plan.profile {
+            println!("   {} {}", "Profile:".bright_cyan(), profile);
+        }
+        println!("   {} {}", "Files: ".bright_cyan(), plan.files.len());
+        println!("   {} {}", "Size:  ".bright_cyan(), HumanBytes(plan.total_bytes()));
+
+        let mut biggest: Vec<_> = plan.files.iter().collect();
+        biggest.sort_by_key(|f| std::cmp::Reverse(f.size));
+        if !biggest.is_empty() {
+            println!("\n{}", "📦 Biggest files:".bright_cyan().bold());
+            for file in biggest.iter().take(MAX_LISTED) {
+                println!("   {} {:>10}  {}", "➤".green(), HumanBytes(file.size).to_string(), file.relative_path);
+            }
+        }
+    }
+
+    pub fn print_restore_plan(plan: &RestorePlan, editor_running: bool) {
+        const MAX_LISTED: usize = 20;
+
+        println!("\n{} {} ({})", "📦 Backup:".bright_cyan().bold(), plan.meta.object_key, HumanBytes(plan.meta.archive_size));
+        println!(
+            "   {} by {} at {}, {} files, {} unpacked",
+            "Created".bright_cyan(),
+            plan.meta.created_by,
+            plan.meta.created_at,
+            plan.meta.files.len(),
+            HumanBytes(plan.meta.total_size())
+        );
+        if let Some(message) = &plan.meta.message {
+            println!("   {} {}", "Message:".bright_cyan(), message);
+        }
+        if !plan.meta.volumes.is_empty() {
+            println!("   {} {} volumes", "Split into".bright_cyan(), plan.meta.volumes.len());
+        }
+        if plan.meta.encrypted {
+            println!("   {}", "🔒 Encrypted, the password is required to restore it".bright_cyan());
+        }
+        if plan.meta.storage == Storage::Chunks {
+            println!("   {} files are rebuilt from the chunks of the bucket's chunk store", "Chunks:".bright_cyan());
+        }
+        if !plan.bases.is_empty() {
+            let unchanged = plan.meta.files.iter().filter(|f| f.stored_in.is_some()).count();
+            println!(
+                "   {} {} unchanged files come from {} earlier backups, {} to download in total",
+                "Incremental:".bright_cyan(),
+                unchanged,
+                plan.bases.len(),
+                HumanBytes(plan.download_size())
+            );
+        }
+        println!("{} {}", "📂 Target:".bright_cyan().bold(), plan.target_path.display());
+        if let Some(profile) = plan.meta.profile.as_ref().filter(|_| !plan.meta.profile_include.is_empty()) {
+            println!(
+                "{} '{}' ({}), files outside it are left untouched",
+                "🧩 Profile:".bright_cyan().bold(),
+                profile,
+                plan.meta.profile_include.join(", ")
+            );
+        }
+
+        if !plan.paths.is_empty() {
+            println!(
+                "{} {} of {} files matching {}",
+                "🎯 Selected:".bright_cyan().bold(),
+                plan.files.len(),
+                plan.meta.files.len(),
+                plan.paths.join(", ")
+            );
+        }
+
+        if plan.overwritten.is_empty() {
+            println!("{}", "No existing files will be overwritten".green());
+        } else {
+            let count = plan.overwritten.len();
+            let message = match plan.policy {
+                OverwritePolicy::Overwrite => format!("⚠️ {} existing files will be overwritten:", count),
+                OverwritePolicy::Skip => format!("{} existing files will be kept, their backup copies are skipped:", count),
+                OverwritePolicy::Newer => format!("⚠️ {} existing files will be overwritten unless changed after the backup:", count),
+                OverwritePolicy::Rename => format!("{} existing files will be kept, backup copies are restored as 'name (1).ext':", count),
+                OverwritePolicy::Trash => format!("⚠️ {} existing files will be replaced, current copies are moved to .rsget/trash:", count),
+            };
+            println!("{}", message.yellow().bold());
+            for file in plan.overwritten.iter().take(MAX_LISTED) {
+                println!("   {} {}", "➤".yellow(), file);
+            }
+            if plan.overwritten.len() > MAX_LISTED {
+                println!("   ... and {} more", plan.overwritten.len() - MAX_LISTED);
+            }
+        }
+
+        if editor_running {
+            println!("{}", "⚠️ Unreal Editor is running, close it before overwriting project files".red().bold());
+        }
+    }
+
     pub fn print_help() {
         println!("\x1b[1;36mAvailable commands:\x1b[0m");
         println!();
-        println!("  \x1b[1;32minit\x1b[0m      - Initialize a new project. Creates config, checks AWS storage, and prepares your branch.");
-        println!("              \x1b[3mUsage: init\x1b[0m");
+        println!("  \x1b[1;32minit\x1b[0m      - Link the current project to remote storage. Checks credentials and bucket access,");
+        println!("              creates the remote project descriptor and writes the local .rsget config.");
+        println!("              \x1b[3mUsage: init [--create-bucket]\x1b[0m");
+        println!("              \x1b[33mNote: --create-bucket creates BUCKET_NAME if it does not exist\x1b[0m");
         println!();
-        println!("  \x1b[1;32mpull\x1b[0m      - Pull the latest changes for the currently selected project.");
-        println!("              \x1b[3mUsage: pull\x1b[0m");
-        println!("              \x1b[33mNote: Requires an active project (use 'set <name>' first)\x1b[0m");
+        println!("  \x1b[1;32mpull\x1b[0m      - Download a snapshot of the current project (the latest by default) and restore it after confirmation.");
+        println!("              \x1b[3mUsage: pull [<snapshot id>|latest] [--dry-run] [--overwrite <overwrite|skip|newer|rename|trash>] [<path glob>...]\x1b[0m");
+        println!("              \x1b[3mExample: pull Content/Maps/** Config/DefaultEngine.ini\x1b[0m");
+        println!("              \x1b[3mExample: pull 20250101T120000Z Content/Maps/**\x1b[0m");
+        println!("              \x1b[3m--dry-run: show the backup, its size and the files it would overwrite\x1b[0m");
+        println!("              \x1b[3m--overwrite: what to do with existing files (default: overwrite)\x1b[0m");
+        println!("              \x1b[33mNote: Requires an active project (use 'set <name>' first) linked with 'init'\x1b[0m");
         println!();
-        println!("  \x1b[1;32mpush\x1b[0m      - Push your changes to the remote repository for the current project.");
-        println!("              \x1b[3mUsage: push\x1b[0m");
-        println!("              \x1b[33mNote: Requires an active project (use 'set <name>' first)\x1b[0m");
+        println!("  \x1b[1;32mpush\x1b[0m      - Compress the files changed since the latest backup and upload them as a new backup.");
+        println!("              \x1b[3mUsage: push [--dry-run] [--profile <content|source|full|...>] [--format <7z|tar.zst|zip>]\x1b[0m");
+        println!("              \x1b[3m            [--method <lzma2|lzma|deflate|bzip2|zstd|store>] [--level <n>] [--threads <n>]\x1b[0m");
+        println!("              \x1b[3m            [--volume-size <MB>] [--symlinks <store|follow|skip>] [--storage <archive|chunks>]\x1b[0m");
+        println!("              \x1b[3m            [--encrypt] [--full] [--message <text>]\x1b[0m");
+        println!("              \x1b[33mNote: profiles, the default format and compression are defined in .rsget/config.json\x1b[0m");
+        println!("              \x1b[3m--dry-run: show the files, total size and target key without uploading\x1b[0m");
+        println!("              \x1b[3m--symlinks: keep links as links, back up what they point to, or leave them out (default: store)\x1b[0m");
+        println!("              \x1b[3m--storage: one archive per backup, or deduplicated chunks shared by every project in the bucket\x1b[0m");
+        println!("              \x1b[3m--full: upload every file, not only the ones changed since the latest backup\x1b[0m");
+        println!("              \x1b[3m--message: describe the snapshot, quote a message with spaces: --message \"Rework the main map\"\x1b[0m");
+        println!("              \x1b[3m--encrypt: protect a 7z or zip archive with a password (asked for, or RSGET_ARCHIVE_PASSWORD)\x1b[0m");
+        println!("              \x1b[33mNote: Requires an active project (use 'set <name>' first) linked with 'init'\x1b[0m");
+        println!();
+        println!("  \x1b[1;32mstatus\x1b[0m    - Show the files added, modified and deleted since the snapshot last pushed or pulled here.");
+        println!("              \x1b[3mUsage: status\x1b[0m");
+        println!("              \x1b[33mNote: works offline, only files whose mtime changed but size didn't are read\x1b[0m");
+        println!();
+        println!("  \x1b[1;32mlog\x1b[0m       - Show the snapshot history of the current project, newest first.");
+        println!("              \x1b[3mUsage: log [--limit <n>]\x1b[0m");
+        println!();
+        println!("  \x1b[1;32mls\x1b[0m        - List the files of a backup without downloading it (only the archive index is read).");
+        println!("              \x1b[3mUsage: ls [<backup id>|latest] [--tree] [<path glob>...]\x1b[0m");
+        println!("              \x1b[3mExample: ls latest --tree Content/Maps/**\x1b[0m");
+        println!("              \x1b[33mNote: tar.zst archives have no index and are read to the end\x1b[0m");
+        println!();
+        println!("  \x1b[1;32mverify\x1b[0m    - Check that a backup restores: decode every file without writing it and compare checksums.");
+        println!("              \x1b[3mUsage: verify [<backup id>] [--archive <local archive path>]\x1b[0m");
+        println!("              \x1b[3m--archive: check a local copy of the archive instead of downloading it\x1b[0m");
+        println!("              \x1b[33mNote: Checks the latest backup when no id is given\x1b[0m");
         println!();
         println!("  \x1b[1;32mset\x1b[0m       - Select a project to work with.");
         println!("              \x1b[3mUsage: set <project_name>\x1b[0m");
@@ -135,4 +540,11 @@ impl Prints {
         println!("  \x1b[1;32mhelp\x1b[0m      - Display this help message.");
         println!("              \x1b[3mUsage: help\x1b[0m");
     }
-}
\ No newline at end of file
+}
+
+fn modified_time(time: Option<SystemTime>) -> String {
+    match time {
+        Some(time) => DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string(),
+        None => "-".to_string(),
+    }
+}
@@ -13,14 +13,14 @@ pub struct Project {
 }
 
 impl Project {
-    pub fn validate_project(paths: &Vec<String>) -> bool {
+    pub fn validate_project(paths: &[String]) -> bool {
         paths.iter().any(|path| {
             let path_obj = Path::new(path);
             path_obj.is_file() &&
-                path_obj.extension().map_or(false, |ext| ext == "uproject")
+                path_obj.extension().is_some_and(|ext| ext == "uproject")
         })
     }
-    pub fn get_project_name(paths: &Vec<String>) -> String {
+    pub fn get_project_name(paths: &[String]) -> String {
         paths.iter().find_map(|path| {
             let path_obj = Path::new(path);
             if path_obj.is_file() && path_obj.extension()? == "uproject" {
@@ -31,6 +31,13 @@ impl Project {
         }).expect("Не найден .uproject файл в переданных путях")
     }
 
+    pub fn get_engine_association(&self) -> Option<String> {
+        let uproject_path = Path::new(&self.path).join(format!("{}.uproject", self.name));
+        let data = fs::read_to_string(uproject_path).ok()?;
+        let uproject: serde_json::Value = serde_json::from_str(&data).ok()?;
+        uproject.get("EngineAssociation")?.as_str().map(|s| s.to_string())
+    }
+
     pub fn save_project_to_json(project_name: &str, path: &str) -> Result<(), Box<dyn Error>> {
         let json_path = Self::get_current_dir()?.join("projects.json");
 
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use crate::tools::chunk_store::Storage;
+use crate::tools::compressing::{ArchiveFormat, CompressionSettings, SymlinkPolicy};
+
+pub const CONFIG_DIR: &str = ".rsget";
+const CONFIG_FILE: &str = "config.json";
+
+/// Local link between a project directory and its remote project, stored in `.rsget/config.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectConfig {
+    pub project_name: String,
+    pub bucket: String,
+    pub remote_prefix: String,
+    /// Archive format used by `push` unless overridden with `--format`.
+    #[serde(default)]
+    pub format: ArchiveFormat,
+    #[serde(default)]
+    pub compression: CompressionSettings,
+    /// Split archives into volumes of this many megabytes, uploaded as separate objects.
+    #[serde(default)]
+    pub volume_size_mb: Option<u64>,
+    /// What `push` does with symlinks and junctions, unless overridden with `--symlinks`.
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+    /// Whether `push` writes archives or chunks to the bucket's chunk store, unless overridden with `--storage`.
+    #[serde(default)]
+    pub storage: Storage,
+    #[serde(default = "default_profiles")]
+    pub profiles: BTreeMap<String, BackupProfile>,
+}
+
+/// Named subset of the project selectable with `push --profile <name>`.
+/// Both lists use `.rsgetignore` syntax and are relative to the project root.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct BackupProfile {
+    /// Only files matching one of these patterns are backed up; empty means the whole project.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Extra ignore rules applied after the defaults and `.rsgetignore`, `!pattern` re-includes.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+fn default_profiles() -> BTreeMap<String, BackupProfile> {
+    let profile = |include: &[&str], exclude: &[&str]| BackupProfile {
+        include: include.iter().map(|p| p.to_string()).collect(),
+        exclude: exclude.iter().map(|p| p.to_string()).collect(),
+    };
+
+    BTreeMap::from([
+        ("content".to_string(), profile(&["/Content/", "/*.uproject"], &[])),
+        (
+            "source".to_string(),
+            profile(&["/Source/", "/Config/", "/Plugins/**/Source/", "/Plugins/**/*.uplugin", "/*.uproject"], &[]),
+        ),
+        ("full".to_string(), profile(&[], &["!Binaries/"])),
+    ])
+}
+
+/// Remote project descriptor, stored as `<remote_prefix>/project.json` in the bucket.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectDescriptor {
+    pub name: String,
+    pub engine_association: String,
+    pub created_by: String,
+    pub created_at: String,
+    pub default_excludes: Vec<String>,
+}
+
+impl ProjectConfig {
+    pub fn new(project_name: &str, bucket: &str) -> Self {
+        ProjectConfig {
+            project_name: project_name.to_string(),
+            bucket: bucket.to_string(),
+            remote_prefix: format!("projects/{}", project_name),
+            format: ArchiveFormat::default(),
+            compression: CompressionSettings::default(),
+            volume_size_mb: None,
+            symlinks: SymlinkPolicy::default(),
+            storage: Storage::default(),
+            profiles: default_profiles(),
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> anyhow::Result<&BackupProfile> {
+        self.profiles.get(name).ok_or_else(|| {
+            let available: Vec<_> = self.profiles.keys().map(|k| k.as_str()).collect();
+            anyhow!("Unknown profile '{}', available: {}", name, available.join(", "))
+        })
+    }
+
+    pub fn config_path(project_dir: &Path) -> PathBuf {
+        project_dir.join(CONFIG_DIR).join(CONFIG_FILE)
+    }
+
+    pub fn load(project_dir: &Path) -> anyhow::Result<Self> {
+        let path = Self::config_path(project_dir);
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Project is not initialized, run 'init' first ({} not found)", path.display()))?;
+        serde_json::from_str(&data).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::config_path(project_dir);
+        fs::create_dir_all(project_dir.join(CONFIG_DIR)).context("Failed to create .rsget directory")?;
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn descriptor_key(&self) -> String {
+        format!("{}/project.json", self.remote_prefix)
+    }
+
+    pub fn backups_prefix(&self) -> String {
+        format!("{}/backups/", self.remote_prefix)
+    }
+
+    /// Key of the archive of backup `backup_id`. `upload_id` is unique to the push, so a push that
+    /// loses the race for an id to another machine never overwrites the winner's archive.
+    pub fn backup_key(&self, backup_id: &str, upload_id: &str, format: ArchiveFormat) -> String {
+        format!("{}{}-{}.{}", self.backups_prefix(), backup_id, upload_id, format.extension())
+    }
+
+    pub fn backup_meta_key(&self, backup_id: &str) -> String {
+        format!("{}{}.json", self.backups_prefix(), backup_id)
+    }
+}
@@ -2,10 +2,9 @@ use std::thread;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::thread::sleep;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
-use crate::{Project};
+use crate::utils::project::Project;
 use crate::utils::router::Router;
 use crate::utils::user::User;
 use ini::Ini;
@@ -27,7 +26,7 @@ impl Engine {
             versions = files
                 .iter()
                 .filter(|e| e.to_lowercase().contains("ue_"))
-                .map(|e| e.split('_').last().unwrap().to_string())
+                .map(|e| e.split('_').next_back().unwrap().to_string())
                 .collect();
         }
         Ok(versions)
@@ -43,7 +42,7 @@ impl Engine {
 
         let conf = match Ini::load_from_file(format!("{}\\EditorSettings.ini", &path)) {
             Ok(c) => c,
-            Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())),
+            Err(e) => return Err(std::io::Error::other(e.to_string())),
         };
 
         let section = Some("/Script/UnrealEd.EditorSettings");
@@ -115,16 +114,6 @@ impl Engine {
             .unwrap_or_else(|| "".to_string())
     }
 
-    pub fn check_ue_process() -> String {
-        let check_process = thread::spawn(move || {
-            loop {
-                let process= Self::find_ue_process();
-                sleep(Duration::from_secs(10));
-                return process;
-            }
-        });
-        check_process.join().unwrap()
-    }
     pub fn monitor_unreal_editor(stop_flag: Arc<AtomicBool>) {
         let mut system = System::new_all();
         let mut was_running = false;
@@ -140,6 +129,7 @@ impl Engine {
                         .summary("RsGet AWS Sync")
                         .body("UnrealEditor.exe запущен!")
                         .show()
+                        .map(|_| ())
                         .unwrap_or_else(|e| eprintln!("Ошибка: {:?}", e));
                     was_running = true;
                 },
@@ -148,6 +138,7 @@ impl Engine {
                         .summary("RsGet AWS Sync")
                         .body("UnrealEditor.exe запущен!")
                         .show()
+                        .map(|_| ())
                         .unwrap_or_else(|e| eprintln!("Ошибка: {:?}", e));
                     was_running = false;
                 },
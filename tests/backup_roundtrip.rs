@@ -0,0 +1,790 @@
+mod common;
+
+use common::faults::Operation;
+use common::{link_project, noise, read_tree, write_file, S3Server, BUCKET};
+use rsget::functions::pull::PullOptions;
+use rsget::functions::push::PushOptions;
+use rsget::functions::list::ListOptions;
+use rsget::functions::log::LogOptions;
+use rsget::functions::verify::VerifyOptions;
+use rsget::functions::status::{ChangeKind, FileChange};
+use rsget::functions::{init, list, log, pull, push, status, verify};
+use rsget::tools::chunk_store::Storage;
+use rsget::tools::compressing::{ArchiveFormat, OverwritePolicy, SymlinkPolicy};
+use rsget::utils::backup_meta::BackupMeta;
+use rsget::utils::project::Project;
+use rsget::utils::project_config::ProjectConfig;
+use std::path::Path;
+
+fn build_project(root: &Path) {
+    write_file(root, "Demo.uproject", br#"{ "FileVersion": 3, "EngineAssociation": "5.5" }"#);
+    write_file(root, "Config/DefaultEngine.ini", b"[/Script/EngineSettings.GameMapsSettings]\nGameDefaultMap=/Game/Maps/Main\n");
+    write_file(root, "Content/Maps/Main.umap", &noise(64 * 1024, 1));
+    write_file(root, "Content/Characters/Hero.uasset", &noise(6 * 1024 * 1024, 2));
+    write_file(root, "Source/Demo/Demo.cpp", b"#include \"Demo.h\"\n");
+    write_file(root, "Binaries/Win64/Demo.exe", b"binary");
+    write_file(root, "Intermediate/Build/cache.bin", b"intermediate");
+    write_file(root, "Saved/Logs/Demo.bak", b"backup");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn init_creates_bucket_descriptor_and_local_config() {
+    let server = S3Server::start().await;
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Demo");
+
+    let project = Project {
+        name: "Demo".to_string(),
+        path: project_dir.path().to_string_lossy().to_string(),
+        last_open_time: String::new(),
+    };
+    let config = init::init_project(&project, true, &server.client()).await.unwrap();
+
+    let descriptor = server.object(BUCKET, &config.descriptor_key()).expect("descriptor uploaded");
+    let descriptor: serde_json::Value = serde_json::from_slice(&descriptor).unwrap();
+    assert_eq!(descriptor["name"], "Demo");
+    assert_eq!(descriptor["engine_association"], "5.5");
+    assert_eq!(ProjectConfig::load(project_dir.path()).unwrap(), config);
+
+    // A config with a typo is reported, not replaced by a default one
+    let config_path = ProjectConfig::config_path(project_dir.path());
+    let broken = std::fs::read_to_string(&config_path).unwrap().replacen('{', "{ \"symlinks\": \"folow\",", 1);
+    std::fs::write(&config_path, &broken).unwrap();
+    let error = init::init_project(&project, true, &server.client()).await.err().unwrap();
+    assert!(format!("{:#}", error).contains("Failed to parse"), "{:#}", error);
+    assert_eq!(std::fs::read_to_string(&config_path).unwrap(), broken);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn push_uploads_archive_and_metadata() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    let config = link_project(project_dir.path(), "PushMeta");
+
+    let summary = push::create_backup(project_dir.path(), &server.client(), &PushOptions::default()).await.unwrap();
+
+    let archive = server.object(BUCKET, &summary.object_key).expect("archive uploaded");
+    assert_eq!(archive.len() as u64, summary.bytes);
+    let reader = sevenz_rust2::ArchiveReader::new(std::io::Cursor::new(archive), sevenz_rust2::Password::empty()).unwrap();
+    let mut entries: Vec<_> = reader.archive().files.iter().map(|f| f.name().to_string()).collect();
+    entries.sort();
+    assert_eq!(entries[0], "Config/DefaultEngine.ini");
+    assert!(entries.iter().all(|name| !name.contains('\\') && !name.starts_with('/')), "{:?}", entries);
+    assert_eq!(server.pending_uploads(), 0);
+
+    assert!(summary.object_key.starts_with(&config.backups_prefix()));
+    let meta = server.snapshot(&summary.object_key);
+    let report = summary.compression.as_ref().expect("push reports compression");
+    assert_eq!(report.input_bytes, meta.total_size());
+    let mut paths: Vec<_> = meta.files.iter().map(|f| f.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "Config/DefaultEngine.ini",
+            "Content/Characters/Hero.uasset",
+            "Content/Maps/Main.umap",
+            "Demo.uproject",
+            "Source/Demo/Demo.cpp",
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn push_with_profile_records_it_in_metadata() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "PushProfile");
+
+    let options = PushOptions { profile: Some("content".to_string()), ..Default::default() };
+    let summary = push::create_backup(project_dir.path(), &server.client(), &options).await.unwrap();
+
+    let meta = server.snapshot(&summary.object_key);
+    assert_eq!(meta.profile.as_deref(), Some("content"));
+    assert_eq!(meta.profile_include, ["/Content/", "/*.uproject"]);
+    let mut paths: Vec<_> = meta.files.iter().map(|f| f.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, ["Content/Characters/Hero.uasset", "Content/Maps/Main.umap", "Demo.uproject"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn push_with_unknown_profile_fails_before_uploading() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "PushUnknown");
+
+    let options = PushOptions { profile: Some("art".to_string()), ..Default::default() };
+    let error = push::create_backup(project_dir.path(), &server.client(), &options).await.unwrap_err();
+
+    assert!(error.to_string().contains("Unknown profile 'art'"), "{:#}", error);
+    assert!(server.keys(BUCKET).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn push_then_pull_restores_identical_tree() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let source_dir = tempfile::tempdir().unwrap();
+    build_project(source_dir.path());
+    link_project(source_dir.path(), "RoundTrip");
+    push::create_backup(source_dir.path(), &client, &PushOptions::default()).await.unwrap();
+
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "RoundTrip");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert!(plan.overwritten.is_empty());
+    pull::restore_backup(plan, &client).await.unwrap();
+
+    let mut expected = read_tree(source_dir.path());
+    expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+    assert_eq!(read_tree(target_dir.path()), expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_archive_format_round_trips() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    for format in ArchiveFormat::ALL {
+        let name = format!("Formats{}", format.extension().replace('.', ""));
+        let source_dir = tempfile::tempdir().unwrap();
+        build_project(source_dir.path());
+        link_project(source_dir.path(), &name);
+        let options = PushOptions { format: Some(format), ..Default::default() };
+        let summary = push::create_backup(source_dir.path(), &client, &options).await.unwrap();
+        assert!(summary.object_key.ends_with(&format!(".{}", format.extension())), "{}", summary.object_key);
+
+        let target_dir = tempfile::tempdir().unwrap();
+        link_project(target_dir.path(), &name);
+        let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+        assert_eq!(plan.meta.format, format);
+        pull::restore_backup(plan, &client).await.unwrap();
+
+        let mut expected = read_tree(source_dir.path());
+        expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+        assert_eq!(read_tree(target_dir.path()), expected, "{}", format);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn push_uses_the_format_from_project_config() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    let mut config = link_project(project_dir.path(), "ConfigFormat");
+    config.format = ArchiveFormat::Zip;
+    config.save(project_dir.path()).unwrap();
+
+    let summary = push::create_backup(project_dir.path(), &server.client(), &PushOptions::default()).await.unwrap();
+
+    assert!(summary.object_key.ends_with(".zip"), "{}", summary.object_key);
+    let meta = server.snapshot(&summary.object_key);
+    assert_eq!(meta.format, ArchiveFormat::Zip);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn split_backups_upload_volumes_and_restore() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let source_dir = tempfile::tempdir().unwrap();
+    build_project(source_dir.path());
+    link_project(source_dir.path(), "Volumes");
+    let options = PushOptions { volume_size_mb: Some(2), ..Default::default() };
+    let summary = push::create_backup(source_dir.path(), &client, &options).await.unwrap();
+
+    let meta = server.snapshot(&summary.object_key);
+    assert_eq!(meta.volumes.len(), 4);
+    assert!(server.object(BUCKET, &summary.object_key).is_none());
+    for (index, volume) in meta.volumes.iter().enumerate() {
+        assert_eq!(volume.key, format!("{}.{:03}", summary.object_key, index + 1));
+        assert_eq!(server.object(BUCKET, &volume.key).unwrap().len() as u64, volume.size);
+    }
+    assert_eq!(meta.volumes.iter().map(|v| v.size).sum::<u64>(), meta.archive_size);
+
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Volumes");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    pull::restore_backup(plan, &client).await.unwrap();
+
+    let mut expected = read_tree(source_dir.path());
+    expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+    assert_eq!(read_tree(target_dir.path()), expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn encrypted_backups_restore_only_with_the_password() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let source_dir = tempfile::tempdir().unwrap();
+    build_project(source_dir.path());
+    link_project(source_dir.path(), "Encrypted");
+    let options = PushOptions { format: Some(ArchiveFormat::Zip), password: Some("hunter2".to_string()), ..Default::default() };
+    let summary = push::create_backup(source_dir.path(), &client, &options).await.unwrap();
+    assert!(summary.compression.as_ref().unwrap().encrypted);
+
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Encrypted");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert!(plan.meta.encrypted);
+    let error = pull::restore_backup(plan, &client).await.unwrap_err();
+    assert!(error.to_string().contains(&summary.object_key), "{:#}", error);
+    assert!(format!("{:#}", error).contains("a password is required"), "{:#}", error);
+
+    let mut plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    plan.password = Some("hunter3".to_string());
+    let error = pull::restore_backup(plan, &client).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("Wrong archive password"), "{:#}", error);
+    assert!(read_tree(target_dir.path()).is_empty());
+
+    let mut plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    plan.password = Some("hunter2".to_string());
+    pull::restore_backup(plan, &client).await.unwrap();
+
+    let mut expected = read_tree(source_dir.path());
+    expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+    assert_eq!(read_tree(target_dir.path()), expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_push_with_another_password_is_not_incremental() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+    let encrypted = |password: &str| PushOptions { password: Some(password.to_string()), ..Default::default() };
+
+    let source_dir = tempfile::tempdir().unwrap();
+    build_project(source_dir.path());
+    link_project(source_dir.path(), "Rekeyed");
+    push::create_backup(source_dir.path(), &client, &encrypted("first")).await.unwrap();
+    write_file(source_dir.path(), "Content/Maps/Main.umap", &noise(64 * 1024, 7));
+    push::create_backup(source_dir.path(), &client, &encrypted("second")).await.unwrap();
+
+    let mut expected = read_tree(source_dir.path());
+    expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Rekeyed");
+    let mut plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert!(plan.meta.base.is_none() && plan.bases.is_empty());
+    plan.password = Some("second".to_string());
+    pull::restore_backup(plan, &client).await.unwrap();
+    assert_eq!(read_tree(target_dir.path()), expected);
+
+    // The same password again builds on the previous backup
+    let previous = pull::find_latest_backup(&ProjectConfig::load(source_dir.path()).unwrap(), &client).await.unwrap();
+    write_file(source_dir.path(), "Source/Demo/Demo.cpp", b"#include \"Demo.h\"\n// edited\n");
+    push::create_backup(source_dir.path(), &client, &encrypted("second")).await.unwrap();
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Rekeyed");
+    let mut plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert_eq!(plan.meta.base, Some(previous.id));
+    plan.password = Some("second".to_string());
+    pull::restore_backup(plan, &client).await.unwrap();
+    expected.insert("Source/Demo/Demo.cpp".to_string(), b"#include \"Demo.h\"\n// edited\n".to_vec());
+    assert_eq!(read_tree(target_dir.path()), expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pull_with_paths_restores_only_matching_files() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Selective");
+    push::create_backup(project_dir.path(), &client, &PushOptions::default()).await.unwrap();
+
+    write_file(project_dir.path(), "Content/Maps/Main.umap", b"broken map");
+    write_file(project_dir.path(), "Source/Demo/Demo.cpp", b"// work in progress\n");
+
+    let options = PullOptions { paths: vec!["Content/Maps/**".to_string()], ..Default::default() };
+    let plan = pull::plan_restore(project_dir.path(), &client, &options).await.unwrap();
+    assert_eq!(plan.files, ["Content/Maps/Main.umap"]);
+    assert_eq!(plan.overwritten, ["Content/Maps/Main.umap"]);
+    pull::restore_backup(plan, &client).await.unwrap();
+
+    let tree = read_tree(project_dir.path());
+    assert_eq!(tree["Content/Maps/Main.umap"], noise(64 * 1024, 1));
+    assert_eq!(tree["Source/Demo/Demo.cpp"], b"// work in progress\n");
+
+    let options = PullOptions { paths: vec!["Content/Missing/**".to_string()], ..Default::default() };
+    let error = pull::plan_restore(project_dir.path(), &client, &options).await.err().unwrap();
+    assert!(error.to_string().starts_with("No files in backup"), "{:#}", error);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pull_with_trash_policy_keeps_the_replaced_files() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Trash");
+    push::create_backup(project_dir.path(), &client, &PushOptions::default()).await.unwrap();
+    write_file(project_dir.path(), "Source/Demo/Demo.cpp", b"// work in progress\n");
+
+    let options = PullOptions { policy: OverwritePolicy::Trash, ..Default::default() };
+    let plan = pull::plan_restore(project_dir.path(), &client, &options).await.unwrap();
+    let summary = pull::restore_backup(plan, &client).await.unwrap();
+
+    let report = summary.extraction.expect("pull reports extraction");
+    assert_eq!((report.extracted, report.trashed), (5, 5));
+    let trash_dir = report.trash_dir.unwrap();
+    assert_eq!(std::fs::read(trash_dir.join("Source/Demo/Demo.cpp")).unwrap(), b"// work in progress\n");
+    assert_eq!(read_tree(project_dir.path())["Source/Demo/Demo.cpp"], b"#include \"Demo.h\"\n");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_checks_the_latest_or_a_given_backup() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Audited");
+    let options = PushOptions { format: Some(ArchiveFormat::SevenZ), password: Some("hunter2".to_string()), ..Default::default() };
+    let summary = push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+    let mut plan = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.unwrap();
+    assert_eq!(plan.meta.object_key, summary.object_key);
+    let files = plan.meta.files.len();
+    let id = plan.meta.id.clone();
+    plan.password = Some("hunter2".to_string());
+    let report = verify::verify_backup(plan, &client).await.unwrap();
+    assert!(report.is_intact());
+    assert_eq!(report.report.verified.len(), files);
+
+    // A local copy is checked without downloading anything
+    let local = project_dir.path().join("Saved/copy.7z");
+    std::fs::write(&local, server.object(BUCKET, &summary.object_key).unwrap()).unwrap();
+    let options = VerifyOptions { backup_id: Some(id), archive: Some(local) };
+    let mut plan = verify::plan_verify(project_dir.path(), &client, &options).await.unwrap();
+    plan.password = Some("hunter3".to_string());
+    let error = verify::verify_backup(plan, &client).await.err().unwrap();
+    assert!(format!("{:#}", error).contains("Wrong archive password"), "{:#}", error);
+
+    let options = VerifyOptions { backup_id: Some("19700101T000000Z".to_string()), archive: None };
+    let error = verify::plan_verify(project_dir.path(), &client, &options).await.err().unwrap();
+    assert!(error.to_string().contains("Backup '19700101T000000Z' not found"), "{:#}", error);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ls_reads_only_the_archive_index() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+    let maps = ListOptions { paths: vec!["Content/Maps/**".to_string()], ..Default::default() };
+
+    let cases = [(ArchiveFormat::SevenZ, None), (ArchiveFormat::SevenZ, Some(2)), (ArchiveFormat::Zip, None), (ArchiveFormat::TarZstd, None)];
+    for (i, (format, volume_size_mb)) in cases.into_iter().enumerate() {
+        let project_dir = tempfile::tempdir().unwrap();
+        build_project(project_dir.path());
+        link_project(project_dir.path(), &format!("Listed{}", i));
+        let options = PushOptions { format: Some(format), volume_size_mb, ..Default::default() };
+        push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+        let plan = list::plan_list(project_dir.path(), &client, &ListOptions::default()).await.unwrap();
+        let mut expected: Vec<_> = plan.meta.files.iter().map(|f| (f.path.clone(), f.size)).collect();
+        expected.sort();
+        let listing = list::list_backup(plan, &client).await.unwrap();
+        let files: Vec<_> = listing.entries.iter().filter(|e| !e.is_dir).map(|e| (e.path.clone(), e.size)).collect();
+        assert_eq!(files, expected, "{}", format);
+        assert!(listing.entries.iter().all(|e| e.modified.is_some()), "{}", format);
+        if format == ArchiveFormat::TarZstd {
+            assert_eq!(listing.fetched, listing.archive_size);
+        } else {
+            assert!(listing.fetched < listing.archive_size / 4, "{}: read {} bytes", format, listing.fetched);
+        }
+
+        let plan = list::plan_list(project_dir.path(), &client, &maps).await.unwrap();
+        let listing = list::list_backup(plan, &client).await.unwrap();
+        let paths: Vec<_> = listing.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["Content/Maps/Main.umap"], "{}", format);
+    }
+
+    // 7z encrypts the entry names, so listing needs the password
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "ListedEncrypted");
+    let options = PushOptions { format: Some(ArchiveFormat::SevenZ), password: Some("hunter2".to_string()), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+    let plan = list::plan_list(project_dir.path(), &client, &ListOptions::default()).await.unwrap();
+    assert!(plan.needs_password());
+    let error = list::list_backup(plan, &client).await.err().unwrap();
+    assert!(format!("{:#}", error).contains("a password is required"), "{:#}", error);
+
+    let mut plan = list::plan_list(project_dir.path(), &client, &ListOptions::default()).await.unwrap();
+    plan.password = Some("hunter2".to_string());
+    assert_eq!(list::list_backup(plan, &client).await.unwrap().entries.len(), 5);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn incremental_pushes_upload_changed_files_and_pull_rebuilds_the_tree() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Incremental");
+    let first = push::create_backup(project_dir.path(), &client, &PushOptions::default()).await.unwrap();
+
+    // Formats can differ between the backups of a chain
+    write_file(project_dir.path(), "Content/Maps/Main.umap", &noise(64 * 1024, 3));
+    write_file(project_dir.path(), "Content/Maps/Arena.umap", &noise(32 * 1024, 4));
+    std::fs::remove_file(project_dir.path().join("Source/Demo/Demo.cpp")).unwrap();
+    let options = PushOptions { format: Some(ArchiveFormat::Zip), ..Default::default() };
+    let second = push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+    assert_eq!(second.compression.as_ref().unwrap().input_bytes, 96 * 1024);
+
+    let plan = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.unwrap();
+    let meta = plan.meta.clone();
+    let first_id = meta.base.clone().expect("second push is incremental");
+    assert!(first.object_key.contains(&first_id));
+    let stored: Vec<_> = meta.archived_files().map(|f| f.path.as_str()).collect();
+    assert_eq!(stored, ["Content/Maps/Arena.umap", "Content/Maps/Main.umap"]);
+    assert!(meta.files.iter().filter(|f| f.stored_in.is_some()).all(|f| f.stored_in.as_deref() == Some(first_id.as_str())));
+    assert!(!meta.files.iter().any(|f| f.path == "Source/Demo/Demo.cpp"));
+    assert!(verify::verify_backup(plan, &client).await.unwrap().is_intact());
+
+    // Nothing changed: an archive without files, every entry points to the earlier backups
+    let options = PushOptions { format: Some(ArchiveFormat::TarZstd), ..Default::default() };
+    let third = push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+    assert_eq!(third.compression.as_ref().unwrap().input_bytes, 0);
+
+    let listing = list::list_backup(list::plan_list(project_dir.path(), &client, &ListOptions::default()).await.unwrap(), &client)
+        .await
+        .unwrap();
+    let files = listing.entries.iter().filter(|e| !e.is_dir).count();
+    assert_eq!(files, 5);
+
+    let mut expected = read_tree(project_dir.path());
+    expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Incremental");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert_eq!(plan.bases.len(), 2);
+    let summary = pull::restore_backup(plan, &client).await.unwrap();
+    assert_eq!(summary.extraction.unwrap().extracted, expected.len());
+    assert_eq!(read_tree(target_dir.path()), expected);
+
+    // A full push stands on its own again
+    let options = PushOptions { full: true, ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert!(plan.meta.base.is_none() && plan.bases.is_empty());
+    assert_eq!(plan.meta.archived_files().count(), expected.len());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_checks_the_unchanged_files_in_the_base_backups() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Audited");
+    let first = push::create_backup(project_dir.path(), &client, &PushOptions::default()).await.unwrap();
+    write_file(project_dir.path(), "Content/Maps/Main.umap", &noise(64 * 1024, 5));
+    push::create_backup(project_dir.path(), &client, &PushOptions::default()).await.unwrap();
+
+    let plan = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.unwrap();
+    assert_eq!(plan.bases.len(), 1);
+    let files = plan.meta.files.len();
+    let summary = verify::verify_backup(plan, &client).await.unwrap();
+    assert!(summary.is_intact());
+    assert_eq!(summary.bases, std::slice::from_ref(&first.object_key));
+    assert_eq!(summary.report.verified.len(), files);
+
+    server.corrupt(BUCKET, &first.object_key);
+    let plan = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.unwrap();
+    let summary = verify::verify_backup(plan, &client).await.unwrap();
+    assert!(!summary.is_intact());
+    assert!(summary.mismatches.iter().any(|m| m.key == first.object_key), "{:?}", summary.mismatches);
+
+    let first_id = server.snapshot(&first.object_key).id;
+    let meta_key = server.keys(BUCKET).into_iter().find(|k| k.ends_with(&format!("{}.json", first_id))).unwrap();
+    server.remove(BUCKET, &meta_key);
+    let error = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.err().unwrap();
+    assert!(format!("{:#}", error).contains("holds unchanged files"), "{:#}", error);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chunked_pushes_upload_only_new_chunks_shared_across_projects() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+    let chunked = PushOptions { storage: Some(Storage::Chunks), ..Default::default() };
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    std::fs::create_dir_all(project_dir.path().join("Content/Empty")).unwrap();
+    link_project(project_dir.path(), "Chunked");
+    let first = push::create_backup(project_dir.path(), &client, &chunked).await.unwrap();
+    let report = first.chunks.unwrap();
+    assert_eq!(report.chunked_files, 5);
+    assert_eq!(report.new_chunks, report.chunks);
+    assert!(report.chunks > 6, "a 6 MB asset is split into chunks");
+    let keys = server.keys(BUCKET);
+    assert!(keys.iter().filter(|k| k.starts_with("projects/Chunked/backups/")).all(|k| k.ends_with(".json")), "{:?}", keys);
+    assert_eq!(keys.iter().filter(|k| k.starts_with("chunks/packs/")).count(), report.packs);
+
+    // A save that rewrites a few KB of a big asset uploads only the chunks around the edit
+    let hero = project_dir.path().join("Content/Characters/Hero.uasset");
+    let mut data = std::fs::read(&hero).unwrap();
+    data[3 * 1024 * 1024..3 * 1024 * 1024 + 4096].copy_from_slice(&noise(4096, 5));
+    std::fs::write(&hero, &data).unwrap();
+    let gets = server.calls(Operation::GetObject);
+    let second = push::create_backup(project_dir.path(), &client, &chunked).await.unwrap();
+    // Only the latest snapshot is read, the pack indexes come from the local cache
+    assert_eq!(server.calls(Operation::GetObject) - gets, 1);
+    let report = second.chunks.unwrap();
+    assert_eq!(report.chunked_files, 1);
+    assert!((1..=2).contains(&report.new_chunks), "{} new chunks", report.new_chunks);
+    assert!(second.bytes <= 2 * 1024 * 1024 + 1024, "uploaded {} bytes", second.bytes);
+
+    // Another project with the same assets stores nothing new
+    let copy_dir = tempfile::tempdir().unwrap();
+    build_project(copy_dir.path());
+    link_project(copy_dir.path(), "ChunkedCopy");
+    let gets = server.calls(Operation::GetObject);
+    let copy = push::create_backup(copy_dir.path(), &client, &chunked).await.unwrap();
+    assert_eq!(server.calls(Operation::GetObject) - gets, server.keys(BUCKET).iter().filter(|k| k.starts_with("chunks/index/")).count());
+    assert_eq!(copy.bytes, 0);
+    assert_eq!(copy.chunks.unwrap().new_chunks, 0);
+
+    let mut expected = read_tree(project_dir.path());
+    expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Chunked");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert_eq!(plan.meta.storage, Storage::Chunks);
+    assert!(plan.bases.is_empty());
+    let summary = pull::restore_backup(plan, &client).await.unwrap();
+    assert_eq!(summary.extraction.unwrap().extracted, expected.len());
+    assert_eq!(read_tree(target_dir.path()), expected);
+    assert!(target_dir.path().join("Content/Empty").is_dir());
+
+    let plan = list::plan_list(project_dir.path(), &client, &ListOptions::default()).await.unwrap();
+    let listing = list::list_backup(plan, &client).await.unwrap();
+    let files: Vec<_> = listing.entries.iter().filter(|e| !e.is_dir).map(|e| e.path.as_str()).collect();
+    assert_eq!(files, expected.keys().map(String::as_str).collect::<Vec<_>>());
+    assert_eq!(listing.fetched, 0);
+
+    let plan = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.unwrap();
+    assert!(verify::verify_backup(plan, &client).await.unwrap().is_intact());
+    // The pack of the edited chunks, which only the latest backup references
+    let pack = server.keys(BUCKET).into_iter().find(|k| k.starts_with("chunks/packs/") && !keys.contains(k)).unwrap();
+    server.corrupt(BUCKET, &pack);
+    let plan = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.unwrap();
+    let summary = verify::verify_backup(plan, &client).await.unwrap();
+    assert!(!summary.is_intact());
+    assert!(summary.report.corrupt.iter().all(|c| c.error.contains("is corrupt")), "{:?}", summary.report.corrupt);
+
+    // An archive can't point into the chunk store, so the next archive push is a full one
+    let options = PushOptions { storage: Some(Storage::Archive), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    assert!(plan.meta.base.is_none());
+    assert_eq!(plan.meta.archived_files().count(), expected.len());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pushes_create_snapshots_that_log_lists_and_pull_restores_by_id() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+    let pushed = |path: &str| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak");
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Snapshots");
+    let mut initial = read_tree(project_dir.path());
+    initial.retain(|path, _| pushed(path));
+    let message = |text: &str| Some(text.to_string());
+    let options = PushOptions { message: message("Initial import"), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+    write_file(project_dir.path(), "Content/Maps/Main.umap", &noise(64 * 1024, 7));
+    let options = PushOptions { message: message("Rework the main map"), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+    let options = PushOptions { message: message("Move to the chunk store"), storage: Some(Storage::Chunks), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+    let snapshots = log::snapshot_log(project_dir.path(), &client, &LogOptions::default()).await.unwrap();
+    let messages: Vec<_> = snapshots.iter().map(|s| s.message.as_deref().unwrap()).collect();
+    assert_eq!(messages, ["Move to the chunk store", "Rework the main map", "Initial import"]);
+    assert!(snapshots.iter().all(|s| BackupMeta::is_id(&s.id) && !s.created_by.is_empty()));
+    assert_eq!(snapshots[0].parent.as_ref(), Some(&snapshots[1].id));
+    assert_eq!(snapshots[1].parent.as_ref(), Some(&snapshots[2].id));
+    assert_eq!(snapshots[2].parent, None);
+    // The tree hash depends on the contents only, not on where they are stored
+    assert_eq!(snapshots[0].tree_hash, snapshots[1].tree_hash);
+    assert_ne!(snapshots[1].tree_hash, snapshots[2].tree_hash);
+    assert!(!BackupMeta::is_id("Content/Maps/**"));
+
+    let options = LogOptions { limit: Some(1) };
+    let latest = log::snapshot_log(project_dir.path(), &client, &options).await.unwrap();
+    assert_eq!(latest.len(), 1);
+    assert_eq!(latest[0].id, snapshots[0].id);
+
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Snapshots");
+    let options = PullOptions { backup_id: Some(snapshots[2].id.clone()), ..Default::default() };
+    let plan = pull::plan_restore(target_dir.path(), &client, &options).await.unwrap();
+    assert_eq!(plan.meta.message.as_deref(), Some("Initial import"));
+    pull::restore_backup(plan, &client).await.unwrap();
+    assert_eq!(read_tree(target_dir.path()), initial);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_pushes_never_overwrite_each_others_snapshots() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    // Both machines see no snapshot yet, so they usually pick the same id
+    let dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+    for (seed, dir) in dirs.iter().enumerate() {
+        build_project(dir.path());
+        write_file(dir.path(), "Content/Maps/Main.umap", &noise(64 * 1024, 10 + seed as u64));
+        link_project(dir.path(), "Race");
+    }
+    let options = PushOptions::default();
+    let (first, second) = tokio::join!(
+        push::create_backup(dirs[0].path(), &client, &options),
+        push::create_backup(dirs[1].path(), &client, &options),
+    );
+
+    let mut pushed = 0;
+    for (dir, result) in dirs.iter().zip([first, second]) {
+        let summary = match result {
+            Ok(summary) => summary,
+            Err(error) => {
+                assert!(error.to_string().contains("already exists"), "{:#}", error);
+                continue;
+            }
+        };
+        pushed += 1;
+        let meta = server.snapshot(&summary.object_key);
+        let target_dir = tempfile::tempdir().unwrap();
+        link_project(target_dir.path(), "Race");
+        let options = PullOptions { backup_id: Some(meta.id), ..Default::default() };
+        let plan = pull::plan_restore(target_dir.path(), &client, &options).await.unwrap();
+        pull::restore_backup(plan, &client).await.unwrap();
+        let mut expected = read_tree(dir.path());
+        expected.retain(|path, _| !path.starts_with("Binaries/") && !path.starts_with("Intermediate/") && !path.ends_with(".bak"));
+        assert_eq!(read_tree(target_dir.path()), expected);
+    }
+    assert!(pushed >= 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn status_compares_the_tree_with_the_last_pushed_or_pulled_snapshot() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Status");
+    let error = status::project_status(project_dir.path()).err().unwrap();
+    assert!(error.to_string().contains("Nothing was pushed or pulled"), "{:#}", error);
+
+    push::create_backup(project_dir.path(), &client, &PushOptions::default()).await.unwrap();
+    let report = status::project_status(project_dir.path()).unwrap();
+    assert!(report.changes.is_empty());
+    assert_eq!((report.unchanged, report.hashed_bytes), (5, 0));
+
+    // Touched without changing, rewritten at the same size, added, deleted and ignored files
+    let source = std::fs::File::options().write(true).open(project_dir.path().join("Source/Demo/Demo.cpp")).unwrap();
+    source.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(3600)).unwrap();
+    drop(source);
+    write_file(project_dir.path(), "Content/Maps/Main.umap", &noise(64 * 1024, 9));
+    write_file(project_dir.path(), "Content/New.uasset", b"new asset");
+    std::fs::remove_file(project_dir.path().join("Config/DefaultEngine.ini")).unwrap();
+    write_file(project_dir.path(), "Binaries/Win64/Demo.pdb", b"symbols");
+
+    let change = |path: &str, kind, size: Option<u64>, snapshot_size: Option<u64>| FileChange { path: path.to_string(), kind, size, snapshot_size };
+    let expected = [
+        change("Config/DefaultEngine.ini", ChangeKind::Deleted, None, Some(73)),
+        change("Content/Maps/Main.umap", ChangeKind::Modified, Some(64 * 1024), Some(64 * 1024)),
+        change("Content/New.uasset", ChangeKind::Added, Some(9), None),
+    ];
+    let report = status::project_status(project_dir.path()).unwrap();
+    assert_eq!(report.changes, expected);
+    assert_eq!(report.unchanged, 3);
+    assert_eq!(report.hashed_bytes, 64 * 1024 + 18);
+
+    // Hashes are cached, so the second run reads nothing
+    let report = status::project_status(project_dir.path()).unwrap();
+    assert_eq!(report.changes, expected);
+    assert_eq!(report.hashed_bytes, 0);
+
+    // A pull records the snapshot it restored
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Status");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    let id = plan.meta.id.clone();
+    pull::restore_backup(plan, &client).await.unwrap();
+    let report = status::project_status(target_dir.path()).unwrap();
+    assert_eq!(report.snapshot.id, id);
+    assert_eq!(report.snapshot.synced_by, "pull");
+    assert!(report.changes.is_empty(), "{:?}", report.changes);
+    assert_eq!(report.unchanged, 5);
+    // The snapshot records the mtimes the files were restored with, not the pusher's
+    assert_eq!(report.hashed_bytes, 0);
+    // A pull that keeps local files or is limited to some paths doesn't record the snapshot
+    write_file(target_dir.path(), "Source/Demo/Demo.cpp", b"#include \"Demo.H\"\n");
+    let options = PullOptions { policy: OverwritePolicy::Skip, ..Default::default() };
+    let plan = pull::plan_restore(target_dir.path(), &client, &options).await.unwrap();
+    pull::restore_backup(plan, &client).await.unwrap();
+    let report = status::project_status(target_dir.path()).unwrap();
+    assert_eq!(report.changes, [change("Source/Demo/Demo.cpp", ChangeKind::Modified, Some(18), Some(18))]);
+
+    let partial_dir = tempfile::tempdir().unwrap();
+    link_project(partial_dir.path(), "Status");
+    let options = PullOptions { paths: vec!["Content/**".to_string()], ..Default::default() };
+    let plan = pull::plan_restore(partial_dir.path(), &client, &options).await.unwrap();
+    pull::restore_backup(plan, &client).await.unwrap();
+    assert!(status::project_status(partial_dir.path()).is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn status_walks_the_tree_with_the_symlink_policy_of_the_snapshot() {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    let client = server.client();
+
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path());
+    link_project(project_dir.path(), "Linked");
+    std::os::unix::fs::symlink("Maps", project_dir.path().join("Content/Levels")).unwrap();
+
+    // The project keeps links as links, this push followed them
+    let options = PushOptions { symlinks: Some(SymlinkPolicy::Follow), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+    let report = status::project_status(project_dir.path()).unwrap();
+    assert_eq!(report.snapshot.symlinks, Some(SymlinkPolicy::Follow));
+    assert!(report.changes.is_empty(), "{:?}", report.changes);
+    assert_eq!(report.unchanged, 6);
+}
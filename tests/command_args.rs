@@ -0,0 +1,49 @@
+use rsget::utils::args::{split_line, CommandArgs};
+
+const PUSH_FLAGS: &[&str] = &["--dry-run", "--full"];
+const PUSH_OPTIONS: &[&str] = &["--profile", "--message"];
+
+fn parse<'a>(words: &'a [String]) -> CommandArgs<'a> {
+    let args: Vec<&str> = words.iter().map(String::as_str).collect();
+    CommandArgs::parse(&args, PUSH_FLAGS, PUSH_OPTIONS).unwrap()
+}
+
+#[test]
+fn quoted_message_is_one_value() {
+    let words = split_line(r#"push --message "Rework the main map" --full"#).unwrap();
+    assert_eq!(words, ["push", "--message", "Rework the main map", "--full"]);
+
+    let args = parse(&words[1..]);
+    assert_eq!(args.value("--message"), Some("Rework the main map"));
+    assert!(args.flag("--full"));
+    assert!(args.positional.is_empty());
+}
+
+#[test]
+fn flags_inside_quotes_stay_in_the_message() {
+    let words = split_line("push --message 'do not pass --full here'").unwrap();
+    let args = parse(&words[1..]);
+    assert_eq!(args.value("--message"), Some("do not pass --full here"));
+    assert!(!args.flag("--full"));
+}
+
+#[test]
+fn unquoted_words_are_not_glued_to_the_message() {
+    let words = split_line("push foo --message bar").unwrap();
+    let args = parse(&words[1..]);
+    assert_eq!(args.value("--message"), Some("bar"));
+    assert_eq!(args.positional, ["foo"]);
+}
+
+#[test]
+fn quotes_join_adjacent_text_and_keep_backslashes() {
+    let words = split_line(r#"  pull  Content/"My Maps"/**   D:\Backups\a.7z "" "#).unwrap();
+    assert_eq!(words, ["pull", "Content/My Maps/**", r"D:\Backups\a.7z", ""]);
+    assert_eq!(split_line(r#"push --message "it's done""#).unwrap()[2], "it's done");
+}
+
+#[test]
+fn unterminated_quote_is_an_error() {
+    assert!(split_line(r#"push --message "Rework the map"#).is_err());
+    assert!(split_line("push --message don't").is_err());
+}
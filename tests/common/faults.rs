@@ -0,0 +1,95 @@
+//! Fault injection for the in-process S3 server.
+//!
+//! Rules target an S3 operation and a range of its calls (1-based, counted per operation),
+//! so a test can say "fail the first two UploadPart requests with 503" and get the same
+//! behaviour on every run.
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use hyper::Method;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    HeadBucket,
+    CreateBucket,
+    ListObjects,
+    ListMultipartUploads,
+    CreateMultipartUpload,
+    UploadPart,
+    ListParts,
+    CompleteMultipartUpload,
+    AbortMultipartUpload,
+    PutObject,
+    GetObject,
+    HeadObject,
+    DeleteObject,
+}
+
+#[derive(Clone, Debug)]
+pub enum Fault {
+    /// Delay the response, then handle the request normally.
+    Latency(Duration),
+    /// Close the connection without sending a response.
+    DropConnection,
+    /// Reply with the given 5xx status without touching the store.
+    ServerError(u16),
+    /// Handle the request, but send only the first `n` body bytes while advertising the full length.
+    TruncateBody(usize),
+    /// Handle the request, but replace the `ETag` header with a bogus value.
+    WrongEtag,
+}
+
+struct Rule {
+    operation: Operation,
+    calls: RangeInclusive<usize>,
+    fault: Fault,
+}
+
+#[derive(Default)]
+pub struct Faults {
+    rules: Vec<Rule>,
+    calls: HashMap<Operation, usize>,
+}
+
+impl Faults {
+    pub fn inject(&mut self, operation: Operation, calls: RangeInclusive<usize>, fault: Fault) {
+        self.rules.push(Rule { operation, calls, fault });
+    }
+
+    pub fn calls(&self, operation: Operation) -> usize {
+        self.calls.get(&operation).copied().unwrap_or(0)
+    }
+
+    /// Counts a call to `operation` and returns the fault to apply to it, if any.
+    pub fn next(&mut self, operation: Operation) -> Option<Fault> {
+        let call = self.calls.entry(operation).or_insert(0);
+        *call += 1;
+        let call = *call;
+        self.rules
+            .iter()
+            .find(|rule| rule.operation == operation && rule.calls.contains(&call))
+            .map(|rule| rule.fault.clone())
+    }
+}
+
+pub fn classify(method: &Method, has_key: bool, query: &HashMap<String, String>) -> Option<Operation> {
+    let operation = match (method, has_key) {
+        (&Method::HEAD, false) => Operation::HeadBucket,
+        (&Method::PUT, false) => Operation::CreateBucket,
+        (&Method::GET, false) if query.contains_key("uploads") => Operation::ListMultipartUploads,
+        (&Method::GET, false) => Operation::ListObjects,
+        (&Method::POST, true) if query.contains_key("uploads") => Operation::CreateMultipartUpload,
+        (&Method::POST, true) if query.contains_key("uploadId") => Operation::CompleteMultipartUpload,
+        (&Method::PUT, true) if query.contains_key("uploadId") => Operation::UploadPart,
+        (&Method::GET, true) if query.contains_key("uploadId") => Operation::ListParts,
+        (&Method::DELETE, true) if query.contains_key("uploadId") => Operation::AbortMultipartUpload,
+        (&Method::PUT, true) => Operation::PutObject,
+        (&Method::GET, true) => Operation::GetObject,
+        (&Method::HEAD, true) => Operation::HeadObject,
+        (&Method::DELETE, true) => Operation::DeleteObject,
+        _ => return None,
+    };
+    Some(operation)
+}
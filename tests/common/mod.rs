@@ -0,0 +1,540 @@
+//! In-process S3-compatible server used by the integration tests.
+//!
+//! Implements just enough of the S3 REST API for the backup pipeline: bucket head/create,
+//! object put (with `If-None-Match: *`)/get/head/delete (with ranged GETs), ListObjectsV2 and
+//! multipart uploads.
+//! Everything is kept in memory and dropped together with the server. Requests pass through
+//! a [`Faults`] layer first, so tests can make chosen calls slow, fail or lie.
+
+#![allow(dead_code)]
+
+pub mod faults;
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aws_sdk_s3::config::retry::RetryConfig;
+use aws_sdk_s3::Client;
+use faults::{Fault, Faults, Operation};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rsget::utils::backup_meta::BackupMeta;
+use rsget::utils::project_config::ProjectConfig;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+pub const BUCKET: &str = "rsget-test";
+
+/// S3 rejects multipart uploads whose non-final parts are smaller than this.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+#[derive(Clone)]
+struct StoredObject {
+    data: Bytes,
+    etag: String,
+}
+
+struct MultipartUpload {
+    bucket: String,
+    key: String,
+    parts: BTreeMap<i32, StoredObject>,
+}
+
+#[derive(Default)]
+struct Store {
+    buckets: HashMap<String, BTreeMap<String, StoredObject>>,
+    uploads: HashMap<String, MultipartUpload>,
+    next_upload_id: u64,
+}
+
+pub struct S3Server {
+    addr: SocketAddr,
+    store: Arc<Mutex<Store>>,
+    faults: Arc<Mutex<Faults>>,
+    accept_loop: JoinHandle<()>,
+}
+
+impl S3Server {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store = Arc::new(Mutex::new(Store::default()));
+        let faults = Arc::new(Mutex::new(Faults::default()));
+
+        let accept_store = store.clone();
+        let accept_faults = faults.clone();
+        let accept_loop = tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else { continue };
+                let store = accept_store.clone();
+                let faults = accept_faults.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| {
+                        let store = store.clone();
+                        let faults = faults.clone();
+                        async move { handle_with_faults(&store, &faults, req).await }
+                    });
+                    let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+                });
+            }
+        });
+
+        S3Server { addr, store, faults, accept_loop }
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Client configured like the real one, but with millisecond retry backoff to keep tests fast.
+    pub fn client(&self) -> Client {
+        let client = rsget::tools::aws::create_client_for(&self.endpoint(), "us-east-1", "test-access-key", "test-secret-key");
+        let retry = RetryConfig::standard()
+            .with_max_attempts(rsget::tools::aws::MAX_ATTEMPTS)
+            .with_initial_backoff(Duration::from_millis(1));
+        Client::from_conf(client.config().to_builder().retry_config(retry).build())
+    }
+
+    pub fn inject(&self, operation: Operation, calls: std::ops::RangeInclusive<usize>, fault: Fault) {
+        self.faults.lock().unwrap().inject(operation, calls, fault);
+    }
+
+    pub fn calls(&self, operation: Operation) -> usize {
+        self.faults.lock().unwrap().calls(operation)
+    }
+
+    pub fn put_object(&self, bucket: &str, key: &str, data: &[u8]) {
+        let mut store = self.store.lock().unwrap();
+        store.buckets.entry(bucket.to_string()).or_default().insert(key.to_string(), stored(Bytes::copy_from_slice(data)));
+    }
+
+    /// Flips one byte of a stored object in place, keeping its size and ETag.
+    pub fn corrupt(&self, bucket: &str, key: &str) {
+        let mut store = self.store.lock().unwrap();
+        let object = store.buckets.get_mut(bucket).unwrap().get_mut(key).unwrap();
+        let mut data = object.data.to_vec();
+        let middle = data.len() / 2;
+        data[middle] ^= 0xff;
+        object.data = Bytes::from(data);
+    }
+
+    pub fn remove(&self, bucket: &str, key: &str) {
+        self.store.lock().unwrap().buckets.get_mut(bucket).unwrap().remove(key);
+    }
+
+    pub fn create_bucket(&self, bucket: &str) {
+        self.store.lock().unwrap().buckets.entry(bucket.to_string()).or_default();
+    }
+
+    pub fn object(&self, bucket: &str, key: &str) -> Option<Vec<u8>> {
+        let store = self.store.lock().unwrap();
+        store.buckets.get(bucket)?.get(key).map(|o| o.data.to_vec())
+    }
+
+    pub fn keys(&self, bucket: &str) -> Vec<String> {
+        let store = self.store.lock().unwrap();
+        store.buckets.get(bucket).map(|b| b.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Metadata of the snapshot whose archive is stored at `object_key`.
+    pub fn snapshot(&self, object_key: &str) -> BackupMeta {
+        let prefix = &object_key[..=object_key.rfind('/').unwrap()];
+        self.keys(BUCKET)
+            .iter()
+            .filter(|key| key.starts_with(prefix) && key.ends_with(".json"))
+            .map(|key| serde_json::from_slice::<BackupMeta>(&self.object(BUCKET, key).unwrap()).unwrap())
+            .find(|meta| meta.object_key == object_key)
+            .expect("snapshot metadata uploaded")
+    }
+
+    pub fn pending_uploads(&self) -> usize {
+        self.store.lock().unwrap().uploads.len()
+    }
+}
+
+impl Drop for S3Server {
+    fn drop(&mut self) {
+        self.accept_loop.abort();
+    }
+}
+
+/// Links `project_dir` to a remote project in [`BUCKET`], the way `init` would.
+pub fn link_project(project_dir: &Path, project_name: &str) -> ProjectConfig {
+    let config = ProjectConfig::new(project_name, BUCKET);
+    config.save(project_dir).unwrap();
+    config
+}
+
+/// Writes `data` to `root/relative`, creating parent directories.
+pub fn write_file(root: &Path, relative: &str, data: &[u8]) {
+    let path = root.join(relative);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, data).unwrap();
+}
+
+/// Deterministic incompressible bytes, so archives are as large as their input.
+pub fn noise(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// Relative path → contents for every file under `root`, skipping the `.rsget` directory.
+pub fn read_tree(root: &Path) -> BTreeMap<String, Vec<u8>> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| !e.path().strip_prefix(root).unwrap().starts_with(".rsget"))
+        .map(|e| {
+            let relative = e.path().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            (relative, std::fs::read(e.path()).unwrap())
+        })
+        .collect()
+}
+
+async fn handle_with_faults(
+    store: &Mutex<Store>,
+    faults: &Mutex<Faults>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, std::io::Error> {
+    // Path-style bucket requests may end in a slash, `/bucket/?list-type=2`
+    let has_key = req.uri().path().trim_start_matches('/').split_once('/').is_some_and(|(_, key)| !key.is_empty());
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let fault = faults::classify(req.method(), has_key, &query).and_then(|op| faults.lock().unwrap().next(op));
+
+    match fault {
+        None => Ok(handle(store, req).await),
+        Some(Fault::Latency(delay)) => {
+            tokio::time::sleep(delay).await;
+            Ok(handle(store, req).await)
+        }
+        Some(Fault::DropConnection) => Err(std::io::Error::other("fault: connection dropped")),
+        Some(Fault::ServerError(status)) => Ok(error(StatusCode::from_u16(status).unwrap(), "InternalError")),
+        Some(Fault::TruncateBody(len)) => {
+            let response = handle(store, req).await;
+            let (parts, body) = response.into_parts();
+            let body = body.collect().await.unwrap().to_bytes();
+            let truncated = body.slice(..len.min(body.len()));
+            Ok(Response::from_parts(parts, Full::new(truncated)))
+        }
+        Some(Fault::WrongEtag) => Ok(with_etag(handle(store, req).await, "\"00000000bad0e7a9\"")),
+    }
+}
+
+async fn handle(store: &Mutex<Store>, req: Request<Incoming>) -> Response<Full<Bytes>> {
+    let method = req.method().clone();
+    let path = req.uri().path().trim_start_matches('/').to_string();
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let range = req
+        .headers()
+        .get("range")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let if_none_match = req.headers().get("if-none-match").is_some_and(|v| v == "*");
+    let body = match req.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(_) => return error(StatusCode::BAD_REQUEST, "IncompleteBody"),
+    };
+
+    let (bucket, key) = match path.split_once('/') {
+        Some((bucket, key)) => (percent_decode(bucket), percent_decode(key)),
+        None => (percent_decode(&path), String::new()),
+    };
+
+    let mut store = store.lock().unwrap();
+    if key.is_empty() {
+        return match method {
+            Method::HEAD if store.buckets.contains_key(&bucket) => empty(StatusCode::OK),
+            Method::HEAD => empty(StatusCode::NOT_FOUND),
+            Method::PUT => {
+                store.buckets.entry(bucket).or_default();
+                empty(StatusCode::OK)
+            }
+            Method::GET if query.contains_key("uploads") => list_uploads(&store, &bucket),
+            Method::GET => list_objects(&store, &bucket, query.get("prefix").map(String::as_str).unwrap_or("")),
+            _ => error(StatusCode::METHOD_NOT_ALLOWED, "MethodNotAllowed"),
+        };
+    }
+
+    if !store.buckets.contains_key(&bucket) {
+        return error(StatusCode::NOT_FOUND, "NoSuchBucket");
+    }
+
+    match method {
+        Method::POST if query.contains_key("uploads") => {
+            store.next_upload_id += 1;
+            let upload_id = format!("upload-{}", store.next_upload_id);
+            store.uploads.insert(
+                upload_id.clone(),
+                MultipartUpload { bucket: bucket.clone(), key: key.clone(), parts: BTreeMap::new() },
+            );
+            xml(format!(
+                "<InitiateMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+                escape(&bucket), escape(&key), upload_id
+            ))
+        }
+        Method::POST if query.contains_key("uploadId") => {
+            let Some(upload) = store.uploads.remove(&query["uploadId"]) else {
+                return error(StatusCode::NOT_FOUND, "NoSuchUpload");
+            };
+            let completed = parse_completed_parts(&String::from_utf8_lossy(&body));
+            let too_small = completed
+                .iter()
+                .rev()
+                .skip(1)
+                .any(|(number, _)| upload.parts.get(number).is_some_and(|p| p.data.len() < MIN_PART_SIZE));
+            if too_small {
+                store.uploads.insert(query["uploadId"].clone(), upload);
+                return error(StatusCode::BAD_REQUEST, "EntityTooSmall");
+            }
+            let mut data = Vec::new();
+            for (part_number, etag) in completed {
+                match upload.parts.get(&part_number) {
+                    Some(part) if part.etag == etag => data.extend_from_slice(&part.data),
+                    _ => {
+                        store.uploads.insert(query["uploadId"].clone(), upload);
+                        return error(StatusCode::BAD_REQUEST, "InvalidPart");
+                    }
+                }
+            }
+            let object = stored(Bytes::from(data));
+            let etag = object.etag.clone();
+            store.buckets.get_mut(&upload.bucket).unwrap().insert(upload.key.clone(), object);
+            xml(format!(
+                "<CompleteMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key><ETag>{}</ETag></CompleteMultipartUploadResult>",
+                escape(&upload.bucket), escape(&upload.key), escape(&etag)
+            ))
+        }
+        Method::PUT if query.contains_key("uploadId") => {
+            let part_number: i32 = query.get("partNumber").and_then(|n| n.parse().ok()).unwrap_or(0);
+            let Some(upload) = store.uploads.get_mut(&query["uploadId"]) else {
+                return error(StatusCode::NOT_FOUND, "NoSuchUpload");
+            };
+            let part = stored(body);
+            let etag = part.etag.clone();
+            upload.parts.insert(part_number, part);
+            with_etag(empty(StatusCode::OK), &etag)
+        }
+        Method::GET if query.contains_key("uploadId") => {
+            let Some(upload) = store.uploads.get(&query["uploadId"]) else {
+                return error(StatusCode::NOT_FOUND, "NoSuchUpload");
+            };
+            let parts: String = upload
+                .parts
+                .iter()
+                .map(|(number, part)| {
+                    format!(
+                        "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag><Size>{}</Size></Part>",
+                        number, escape(&part.etag), part.data.len()
+                    )
+                })
+                .collect();
+            xml(format!(
+                "<ListPartsResult><Bucket>{}</Bucket><Key>{}</Key><UploadId>{}</UploadId><IsTruncated>false</IsTruncated>{}</ListPartsResult>",
+                escape(&bucket), escape(&key), escape(&query["uploadId"]), parts
+            ))
+        }
+        Method::DELETE if query.contains_key("uploadId") => {
+            store.uploads.remove(&query["uploadId"]);
+            empty(StatusCode::NO_CONTENT)
+        }
+        Method::PUT if if_none_match && store.buckets[&bucket].contains_key(&key) => {
+            error(StatusCode::PRECONDITION_FAILED, "PreconditionFailed")
+        }
+        Method::PUT => {
+            let object = stored(body);
+            let etag = object.etag.clone();
+            store.buckets.get_mut(&bucket).unwrap().insert(key, object);
+            with_etag(empty(StatusCode::OK), &etag)
+        }
+        Method::DELETE => {
+            store.buckets.get_mut(&bucket).unwrap().remove(&key);
+            empty(StatusCode::NO_CONTENT)
+        }
+        Method::GET | Method::HEAD => {
+            let Some(object) = store.buckets[&bucket].get(&key).cloned() else {
+                return if method == Method::HEAD {
+                    empty(StatusCode::NOT_FOUND)
+                } else {
+                    error(StatusCode::NOT_FOUND, "NoSuchKey")
+                };
+            };
+            get_object(&object, range.as_deref(), method == Method::HEAD)
+        }
+        _ => error(StatusCode::METHOD_NOT_ALLOWED, "MethodNotAllowed"),
+    }
+}
+
+fn get_object(object: &StoredObject, range: Option<&str>, head_only: bool) -> Response<Full<Bytes>> {
+    let total = object.data.len() as u64;
+    let (status, start, end) = match range.and_then(|r| parse_range(r, total)) {
+        Some((start, end)) => (StatusCode::PARTIAL_CONTENT, start, end),
+        None if range.is_some() => return error(StatusCode::RANGE_NOT_SATISFIABLE, "InvalidRange"),
+        None => (StatusCode::OK, 0, total.saturating_sub(1)),
+    };
+    let data = if total == 0 { Bytes::new() } else { object.data.slice(start as usize..=end as usize) };
+
+    let mut builder = Response::builder()
+        .status(status)
+        .header("etag", &object.etag)
+        .header("accept-ranges", "bytes")
+        .header("content-length", data.len());
+    if status == StatusCode::PARTIAL_CONTENT {
+        builder = builder.header("content-range", format!("bytes {}-{}/{}", start, end, total));
+    }
+    let body = if head_only { Bytes::new() } else { data };
+    builder.body(Full::new(body)).unwrap()
+}
+
+fn list_objects(store: &Store, bucket: &str, prefix: &str) -> Response<Full<Bytes>> {
+    let Some(objects) = store.buckets.get(bucket) else {
+        return error(StatusCode::NOT_FOUND, "NoSuchBucket");
+    };
+    let contents: String = objects
+        .iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(key, object)| {
+            format!(
+                "<Contents><Key>{}</Key><Size>{}</Size><ETag>{}</ETag><LastModified>2025-01-01T00:00:00.000Z</LastModified></Contents>",
+                escape(key), object.data.len(), escape(&object.etag)
+            )
+        })
+        .collect();
+    let count = objects.keys().filter(|key| key.starts_with(prefix)).count();
+    xml(format!(
+        "<ListBucketResult><Name>{}</Name><Prefix>{}</Prefix><KeyCount>{}</KeyCount><IsTruncated>false</IsTruncated>{}</ListBucketResult>",
+        escape(bucket), escape(prefix), count, contents
+    ))
+}
+
+fn list_uploads(store: &Store, bucket: &str) -> Response<Full<Bytes>> {
+    let uploads: String = store
+        .uploads
+        .iter()
+        .filter(|(_, upload)| upload.bucket == bucket)
+        .map(|(id, upload)| format!("<Upload><Key>{}</Key><UploadId>{}</UploadId></Upload>", escape(&upload.key), id))
+        .collect();
+    xml(format!(
+        "<ListMultipartUploadsResult><Bucket>{}</Bucket><IsTruncated>false</IsTruncated>{}</ListMultipartUploadsResult>",
+        escape(bucket), uploads
+    ))
+}
+
+fn stored(data: Bytes) -> StoredObject {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    StoredObject { etag: format!("\"{:016x}\"", hasher.finish()), data }
+}
+
+fn empty(status: StatusCode) -> Response<Full<Bytes>> {
+    Response::builder().status(status).body(Full::new(Bytes::new())).unwrap()
+}
+
+fn with_etag(mut response: Response<Full<Bytes>>, etag: &str) -> Response<Full<Bytes>> {
+    response.headers_mut().insert("etag", etag.parse().unwrap());
+    response
+}
+
+fn xml(body: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/xml")
+        .body(Full::new(Bytes::from(format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>{}", body))))
+        .unwrap()
+}
+
+fn error(status: StatusCode, code: &str) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/xml")
+        .body(Full::new(Bytes::from(format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>{}</Code><Message>{}</Message></Error>",
+            code, code
+        ))))
+        .unwrap()
+}
+
+fn parse_range(header: &str, total: u64) -> Option<(u64, u64)> {
+    let (start, end) = header.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.is_empty(), end.is_empty()) {
+        (true, false) => (total.checked_sub(end.parse().ok()?)?, total - 1),
+        (false, true) => (start.parse().ok()?, total.checked_sub(1)?),
+        (false, false) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(total.checked_sub(1)?)),
+        (true, true) => return None,
+    };
+    (start <= end && end < total).then_some((start, end))
+}
+
+fn parse_completed_parts(body: &str) -> Vec<(i32, String)> {
+    body.split("<Part>")
+        .skip(1)
+        .filter_map(|part| {
+            let number = between(part, "<PartNumber>", "</PartNumber>")?.parse().ok()?;
+            let etag = between(part, "<ETag>", "</ETag>")?.replace("&quot;", "\"");
+            Some((number, etag))
+        })
+        .collect()
+}
+
+fn between<'a>(text: &'a str, open: &str, close: &str) -> Option<&'a str> {
+    let start = text.find(open)? + open.len();
+    let end = start + text[start..].find(close)?;
+    Some(&text[start..end])
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) => (percent_decode(name), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match u8::from_str_radix(&text[i + 1..i + 3], 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
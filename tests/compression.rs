@@ -0,0 +1,574 @@
+mod common;
+
+use common::{noise, write_file};
+use rsget::tools::compressing::{
+    compress_project, extract_archive, safe_entry_path, verify_archive, ArchiveFormat, CompressionMethod, CompressionSettings,
+    ExtractOptions, OverwritePolicy, PasswordError, SymlinkPolicy, TRASH_DIR,
+};
+use rsget::tools::ignore_rules::PathSelection;
+use rsget::tools::progress::Progress;
+use rsget::utils::project_config::BackupProfile;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn build_project(root: &Path) {
+    write_file(root, "Demo.uproject", b"{}");
+    write_file(root, "Config/DefaultEngine.ini", &b"[/Script/Engine.RendererSettings]\n".repeat(200));
+    write_file(root, "Movies/Intro.MP4", &noise(256 * 1024, 1));
+    write_file(root, "Content/Audio/Theme.wav", &noise(128 * 1024, 2));
+}
+
+#[tokio::test]
+async fn zip_stores_incompressible_extensions_and_reports_them() {
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    let output = tempfile::tempdir().unwrap();
+    let archive_path = output.path().join("backup.zip");
+    let settings = CompressionSettings { level: Some(9), ..Default::default() };
+
+    let archive = compress_project(
+        project.path(),
+        &archive_path,
+        &BackupProfile::default(),
+        SymlinkPolicy::Store,
+        ArchiveFormat::Zip,
+        &settings,
+        None,
+    )
+    .await
+    .unwrap();
+    let report = archive.report;
+
+    assert_eq!(archive.files.len(), 4);
+    assert_eq!((report.method, report.level), (CompressionMethod::Deflate, 9));
+    assert_eq!(report.stored_files, 2);
+    assert_eq!(report.stored_bytes, 384 * 1024);
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&archive_path).unwrap()).unwrap();
+    let method = |archive: &mut zip::ZipArchive<_>, name: &str| archive.by_name(name).unwrap().compression();
+    assert_eq!(method(&mut archive, "Movies/Intro.MP4"), zip::CompressionMethod::Stored);
+    assert_eq!(method(&mut archive, "Content/Audio/Theme.wav"), zip::CompressionMethod::Stored);
+    assert_eq!(method(&mut archive, "Config/DefaultEngine.ini"), zip::CompressionMethod::Deflated);
+}
+
+#[tokio::test]
+async fn seven_z_accepts_lzma_and_store_rules_do_not_apply_to_tar_zst() {
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    let output = tempfile::tempdir().unwrap();
+
+    let lzma = CompressionSettings { method: Some(CompressionMethod::Lzma), level: Some(1), ..Default::default() };
+    let report = compress_project(
+        project.path(),
+        &output.path().join("backup.7z"),
+        &BackupProfile::default(),
+        SymlinkPolicy::Store,
+        ArchiveFormat::SevenZ,
+        &lzma,
+        None,
+    )
+    .await
+    .unwrap()
+    .report;
+    assert_eq!((report.method, report.level, report.stored_files), (CompressionMethod::Lzma, 1, 2));
+
+    let report = compress_project(
+        project.path(),
+        &output.path().join("backup.tar.zst"),
+        &BackupProfile::default(),
+        SymlinkPolicy::Store,
+        ArchiveFormat::TarZstd,
+        &CompressionSettings::default(),
+        None,
+    )
+    .await
+    .unwrap()
+    .report;
+    assert_eq!((report.method, report.level, report.stored_files), (CompressionMethod::Zstd, 3, 0));
+}
+
+#[test]
+fn unsupported_methods_and_levels_are_rejected() {
+    let deflate = CompressionSettings { method: Some(CompressionMethod::Deflate), ..Default::default() };
+    let error = deflate.resolve(ArchiveFormat::SevenZ.compressor().as_ref()).unwrap_err();
+    assert_eq!(error.to_string(), "Compression method 'deflate' is not supported by 7z, use one of: lzma2, lzma, store");
+
+    let level = CompressionSettings { level: Some(23), ..Default::default() };
+    let error = level.resolve(ArchiveFormat::TarZstd.compressor().as_ref()).unwrap_err();
+    assert_eq!(error.to_string(), "Compression level 23 is out of range for zstd, expected 1..=22");
+
+    let threads = CompressionSettings { threads: Some(0), ..Default::default() };
+    let error = threads.resolve(ArchiveFormat::Zip.compressor().as_ref()).unwrap_err();
+    assert_eq!(error.to_string(), "Compression thread count must be at least 1");
+
+    let encrypted = CompressionSettings { password: Some("secret".to_string()), ..Default::default() };
+    let error = encrypted.resolve(ArchiveFormat::TarZstd.compressor().as_ref()).unwrap_err();
+    assert_eq!(error.to_string(), "tar.zst archives can't be encrypted, use one of: 7z, zip");
+
+    assert!("brotli".parse::<CompressionMethod>().is_err());
+}
+
+#[tokio::test]
+async fn parallel_compression_keeps_every_entry() {
+    let project = tempfile::tempdir().unwrap();
+    for i in 0..40 {
+        write_file(project.path(), &format!("Content/Props/Prop{:02}.uasset", i), &noise(8 * 1024 + i * 512, i as u64));
+    }
+    write_file(project.path(), "Movies/Intro.mp4", &noise(64 * 1024, 99));
+    let expected = common::read_tree(project.path());
+
+    for format in ArchiveFormat::ALL {
+        let output = tempfile::tempdir().unwrap();
+        let archive_path = output.path().join(format!("backup.{}", format.extension()));
+        let settings = CompressionSettings { threads: Some(3), ..Default::default() };
+
+        let report = compress_project(project.path(), &archive_path, &BackupProfile::default(), SymlinkPolicy::Store, format, &settings, None)
+            .await
+            .unwrap()
+            .report;
+        assert_eq!(report.threads, 3);
+        assert_eq!(std::fs::read_dir(output.path()).unwrap().count(), 1, "{}: part files left behind", format);
+
+        let target = tempfile::tempdir().unwrap();
+        extract_archive(&archive_path, target.path(), format, &ExtractOptions::default()).await.unwrap();
+        assert_eq!(common::read_tree(target.path()), expected, "{}", format);
+    }
+}
+
+#[tokio::test]
+async fn volumes_concatenate_back_into_the_archive() {
+    const VOLUME_SIZE: u64 = 100 * 1024;
+
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    let expected = common::read_tree(project.path());
+
+    for format in ArchiveFormat::ALL {
+        let output = tempfile::tempdir().unwrap();
+        let archive_path = output.path().join(format!("backup.{}", format.extension()));
+        let settings = CompressionSettings { threads: Some(2), ..Default::default() };
+
+        let archive = compress_project(
+            project.path(),
+            &archive_path,
+            &BackupProfile::default(),
+            SymlinkPolicy::Store,
+            format,
+            &settings,
+            Some(VOLUME_SIZE),
+        )
+        .await
+        .unwrap();
+
+        assert!(archive.volumes.len() >= 3, "{}: {:?}", format, archive.volumes);
+        assert!(!archive_path.exists());
+        let mut joined = Vec::new();
+        for (index, volume) in archive.volumes.iter().enumerate() {
+            assert!(volume.to_string_lossy().ends_with(&format!(".{}.{:03}", format.extension(), index + 1)));
+            let data = std::fs::read(volume).unwrap();
+            if index + 1 < archive.volumes.len() {
+                assert_eq!(data.len() as u64, VOLUME_SIZE);
+            }
+            joined.extend(data);
+        }
+        std::fs::write(&archive_path, joined).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        extract_archive(&archive_path, target.path(), format, &ExtractOptions::default()).await.unwrap();
+        assert_eq!(common::read_tree(target.path()), expected, "{}", format);
+    }
+}
+
+#[tokio::test]
+async fn encrypted_archives_need_the_right_password() {
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    let expected = common::read_tree(project.path());
+
+    for format in [ArchiveFormat::SevenZ, ArchiveFormat::Zip] {
+        let output = tempfile::tempdir().unwrap();
+        let archive_path = output.path().join(format!("backup.{}", format.extension()));
+        let settings = CompressionSettings {
+            threads: Some(2),
+            password: Some("correct horse".to_string()),
+            ..Default::default()
+        };
+        let report = compress_project(project.path(), &archive_path, &BackupProfile::default(), SymlinkPolicy::Store, format, &settings, None)
+            .await
+            .unwrap()
+            .report;
+        assert!(report.encrypted);
+
+        // 7z encrypts the header too, zip only the entry data
+        let archive = std::fs::read(&archive_path).unwrap();
+        let names_visible = archive.windows(12).any(|w| w == b"DefaultEngin");
+        assert_eq!(names_visible, format == ArchiveFormat::Zip, "{}", format);
+
+        for (password, error) in [(None, PasswordError::Required), (Some("battery staple"), PasswordError::Wrong)] {
+            let target = tempfile::tempdir().unwrap();
+            let options = ExtractOptions { password: password.map(str::to_string), ..Default::default() };
+            let result = extract_archive(&archive_path, target.path(), format, &options).await.unwrap_err();
+            assert_eq!(result.downcast_ref::<PasswordError>(), Some(&error), "{}: {:#}", format, result);
+        }
+
+        let target = tempfile::tempdir().unwrap();
+        let options = ExtractOptions { password: Some("correct horse".to_string()), ..Default::default() };
+        extract_archive(&archive_path, target.path(), format, &options).await.unwrap();
+        assert_eq!(common::read_tree(target.path()), expected, "{}", format);
+    }
+}
+
+#[tokio::test]
+async fn verification_reports_the_entry_a_flipped_byte_landed_in() {
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    let movie = noise(256 * 1024, 1);
+
+    for format in ArchiveFormat::ALL {
+        let output = tempfile::tempdir().unwrap();
+        let archive_path = output.path().join(format!("backup.{}", format.extension()));
+        compress_project(project.path(), &archive_path, &BackupProfile::default(), SymlinkPolicy::Store, format, &CompressionSettings::default(), None)
+            .await
+            .unwrap();
+
+        let report = verify_archive(&archive_path, format, &ExtractOptions::default()).await.unwrap();
+        assert!(report.corrupt.is_empty(), "{}: {:?}", format, report.corrupt);
+        assert_eq!(report.verified.len(), 4, "{}", format);
+        assert_eq!(report.bytes, common::read_tree(project.path()).values().map(|d| d.len() as u64).sum::<u64>());
+
+        // The movie is stored as is (zstd emits raw blocks for it), so its bytes can be found in the archive
+        let mut archive = std::fs::read(&archive_path).unwrap();
+        let sample = &movie[100_000..100_064];
+        let offset = archive.windows(sample.len()).position(|w| w == sample).unwrap();
+        archive[offset + 10] ^= 0xff;
+        std::fs::write(&archive_path, archive).unwrap();
+
+        let report = verify_archive(&archive_path, format, &ExtractOptions::default()).await.unwrap();
+        let damaged: Vec<_> = report.corrupt.iter().map(|c| c.name.as_deref()).collect();
+        match format {
+            // Raw zstd blocks carry no checksum of their own, only the frame checksum at the end catches it
+            ArchiveFormat::TarZstd => assert_eq!(damaged, [None], "{}", format),
+            _ => assert_eq!(damaged, [Some("Movies/Intro.MP4")], "{}", format),
+        }
+    }
+}
+
+#[tokio::test]
+async fn selected_paths_are_the_only_ones_extracted() {
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    write_file(project.path(), "Content/Maps/Main.umap", &noise(32 * 1024, 3));
+    write_file(project.path(), "Content/Maps/Arena/Arena.umap", &noise(32 * 1024, 4));
+    let expected = common::read_tree(project.path());
+
+    let patterns = ["Content/Maps/**".to_string(), "Config/DefaultEngine.ini".to_string()];
+    let selection = PathSelection::new(&patterns).unwrap();
+    assert!(selection.matches("Content/Maps/Arena/Arena.umap"));
+    assert!(!selection.matches("Content/Audio/Theme.wav"));
+    assert!(!selection.matches("Saved/Config/DefaultEngine.ini"));
+
+    for format in ArchiveFormat::ALL {
+        let output = tempfile::tempdir().unwrap();
+        let archive_path = output.path().join(format!("backup.{}", format.extension()));
+        let settings = CompressionSettings { threads: Some(1), ..Default::default() };
+        compress_project(project.path(), &archive_path, &BackupProfile::default(), SymlinkPolicy::Store, format, &settings, None)
+            .await
+            .unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let options = ExtractOptions { selection: Some(selection.clone()), ..Default::default() };
+        extract_archive(&archive_path, target.path(), format, &options).await.unwrap();
+
+        let mut selected = expected.clone();
+        selected.retain(|path, _| path.starts_with("Content/Maps/") || path == "Config/DefaultEngine.ini");
+        assert_eq!(common::read_tree(target.path()), selected, "{}", format);
+    }
+}
+
+#[test]
+fn entry_paths_outside_the_target_are_rejected() {
+    assert_eq!(safe_entry_path("Content/Maps/Main.umap").unwrap(), Path::new("Content/Maps/Main.umap"));
+    assert_eq!(safe_entry_path("Config\\DefaultEngine.ini").unwrap(), Path::new("Config/DefaultEngine.ini"));
+    assert_eq!(safe_entry_path("./Source//Demo.cpp").unwrap(), Path::new("Source/Demo.cpp"));
+
+    for name in ["../evil.txt", "Content/../../evil.txt", "..\\evil.txt", "/etc/evil", "\\evil", "C:\\evil.txt", "c:evil.txt", "", "./"] {
+        assert!(safe_entry_path(name).is_err(), "{:?} accepted", name);
+    }
+}
+
+#[tokio::test]
+async fn extraction_fails_on_entries_escaping_the_target() {
+    let output = tempfile::tempdir().unwrap();
+    let target = output.path().join("target");
+
+    let zip_path = output.path().join("evil.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    zip.start_file("../evil.txt", zip::write::SimpleFileOptions::default()).unwrap();
+    std::io::Write::write_all(&mut zip, b"evil").unwrap();
+    zip.finish().unwrap();
+
+    let seven_z_path = output.path().join("evil.7z");
+    let mut seven_z = sevenz_rust2::ArchiveWriter::create(&seven_z_path).unwrap();
+    let mut entry = sevenz_rust2::ArchiveEntry::new_file("/evil.txt");
+    entry.size = 4;
+    seven_z.push_archive_entry(entry, Some(&b"evil"[..])).unwrap();
+    seven_z.finish().unwrap();
+
+    // tar::Builder refuses such names, so the header is filled in by hand
+    let tar_path = output.path().join("evil.tar.zst");
+    let mut header = tar::Header::new_gnu();
+    let name = b"..\\evil.txt";
+    header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name);
+    header.set_size(4);
+    header.set_mode(0o644);
+    header.set_cksum();
+    let mut tar = tar::Builder::new(zstd::Encoder::new(std::fs::File::create(&tar_path).unwrap(), 3).unwrap());
+    tar.append(&header, &b"evil"[..]).unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let cases = [(&zip_path, ArchiveFormat::Zip), (&seven_z_path, ArchiveFormat::SevenZ), (&tar_path, ArchiveFormat::TarZstd)];
+    for (path, format) in cases {
+        let error = extract_archive(path, &target, format, &ExtractOptions::default()).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("Unsafe path in archive"), "{}: {:#}", format, error);
+    }
+    assert!(!output.path().join("evil.txt").exists());
+    assert!(common::read_tree(&target).is_empty());
+}
+
+#[tokio::test]
+async fn overwrite_policies_decide_what_happens_to_local_files() {
+    let project = tempfile::tempdir().unwrap();
+    write_file(project.path(), "Content/Old.uasset", b"backup old");
+    write_file(project.path(), "Content/Edited.uasset", b"backup edited");
+    write_file(project.path(), "Content/New.uasset", b"backup new");
+
+    for format in ArchiveFormat::ALL {
+        let output = tempfile::tempdir().unwrap();
+        let archive_path = output.path().join(format!("backup.{}", format.extension()));
+        let settings = CompressionSettings { threads: Some(1), ..Default::default() };
+        compress_project(project.path(), &archive_path, &BackupProfile::default(), SymlinkPolicy::Store, format, &settings, None)
+            .await
+            .unwrap();
+
+        for policy in OverwritePolicy::ALL {
+            let target = tempfile::tempdir().unwrap();
+            write_file(target.path(), "Content/Old.uasset", b"local old");
+            write_file(target.path(), "Content/Edited.uasset", b"local edited");
+            let set_modified = |path: &str, time: SystemTime| {
+                let file = std::fs::File::options().write(true).open(target.path().join(path)).unwrap();
+                file.set_modified(time).unwrap();
+            };
+            set_modified("Content/Old.uasset", UNIX_EPOCH + Duration::from_secs(946_684_800));
+            set_modified("Content/Edited.uasset", SystemTime::now() + Duration::from_secs(3600));
+
+            let options = ExtractOptions { policy, ..Default::default() };
+            let report = extract_archive(&archive_path, target.path(), format, &options).await.unwrap();
+
+            let tree = common::read_tree(target.path());
+            let file = |path: &str| tree.get(path).map(|data| String::from_utf8_lossy(data).into_owned());
+            let case = format!("{} {}", format, policy);
+            assert_eq!(file("Content/New.uasset").as_deref(), Some("backup new"), "{}", case);
+            let (old, edited) = match policy {
+                OverwritePolicy::Overwrite | OverwritePolicy::Trash => ("backup old", "backup edited"),
+                OverwritePolicy::Skip | OverwritePolicy::Rename => ("local old", "local edited"),
+                OverwritePolicy::Newer => ("backup old", "local edited"),
+            };
+            assert_eq!(file("Content/Old.uasset").as_deref(), Some(old), "{}", case);
+            assert_eq!(file("Content/Edited.uasset").as_deref(), Some(edited), "{}", case);
+
+            match policy {
+                OverwritePolicy::Overwrite => assert_eq!((report.extracted, report.kept), (3, 0), "{}", case),
+                OverwritePolicy::Skip => assert_eq!((report.extracted, report.kept), (1, 2), "{}", case),
+                OverwritePolicy::Newer => assert_eq!((report.extracted, report.kept), (2, 1), "{}", case),
+                OverwritePolicy::Rename => {
+                    assert_eq!((report.extracted, report.renamed), (3, 2), "{}", case);
+                    assert_eq!(file("Content/Old (1).uasset").as_deref(), Some("backup old"), "{}", case);
+                    assert_eq!(file("Content/Edited (1).uasset").as_deref(), Some("backup edited"), "{}", case);
+                }
+                OverwritePolicy::Trash => {
+                    assert_eq!((report.extracted, report.trashed), (3, 2), "{}", case);
+                    let trash_dir = report.trash_dir.as_ref().expect("trash dir reported");
+                    assert!(trash_dir.starts_with(target.path().join(TRASH_DIR)), "{}", case);
+                    assert_eq!(std::fs::read(trash_dir.join("Content/Old.uasset")).unwrap(), b"local old");
+                    assert_eq!(std::fs::read(trash_dir.join("Content/Edited.uasset")).unwrap(), b"local edited");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn progress_counts_bytes_read_from_every_thread() {
+    let progress = Progress::new("Testing", Some(4 * 300 * 1024));
+    std::thread::scope(|scope| {
+        for seed in 0..4 {
+            let progress = &progress;
+            scope.spawn(move || {
+                let data = noise(300 * 1024, seed);
+                let mut reader = progress.reader(&format!("file{}", seed), &data[..]);
+                std::io::copy(&mut reader, &mut std::io::sink()).unwrap();
+            });
+        }
+    });
+    assert_eq!(progress.position(), 4 * 300 * 1024);
+    progress.finish("Done");
+}
+
+#[tokio::test]
+async fn extraction_restores_mtimes_permissions_and_empty_dirs() {
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    write_file(project.path(), "Scripts/cook.sh", b"#!/bin/sh\n");
+    std::fs::create_dir_all(project.path().join("Content/Empty/Nested")).unwrap();
+    std::fs::create_dir_all(project.path().join("Intermediate/Empty")).unwrap();
+
+    // An even second, zip keeps times with two-second precision
+    let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    for path in common::read_tree(project.path()).keys() {
+        let file = std::fs::File::options().write(true).open(project.path().join(path)).unwrap();
+        file.set_modified(modified).unwrap();
+    }
+    let readonly = project.path().join("Config/DefaultEngine.ini");
+    let mut permissions = std::fs::metadata(&readonly).unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&readonly, permissions).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(project.path().join("Scripts/cook.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    for format in ArchiveFormat::ALL {
+        let output = tempfile::tempdir().unwrap();
+        let archive_path = output.path().join(format!("backup.{}", format.extension()));
+        compress_project(project.path(), &archive_path, &BackupProfile::default(), SymlinkPolicy::Store, format, &CompressionSettings::default(), None)
+            .await
+            .unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        // Extracting twice replaces the read-only file the first run restored
+        for _ in 0..2 {
+            extract_archive(&archive_path, target.path(), format, &ExtractOptions::default()).await.unwrap();
+        }
+
+        for path in common::read_tree(target.path()).keys() {
+            let metadata = std::fs::metadata(target.path().join(path)).unwrap();
+            assert_eq!(metadata.modified().unwrap(), modified, "{}: {}", format, path);
+            assert_eq!(metadata.permissions().readonly(), path == "Config/DefaultEngine.ini", "{}: {}", format, path);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(target.path().join("Scripts/cook.sh")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755, "{}", format);
+        }
+        assert!(target.path().join("Content/Empty/Nested").is_dir(), "{}", format);
+        assert!(!target.path().join("Intermediate").exists(), "{}", format);
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlink_policy_stores_follows_or_skips_links() {
+    let project = tempfile::tempdir().unwrap();
+    build_project(project.path());
+    std::os::unix::fs::symlink("Audio", project.path().join("Content/Music")).unwrap();
+    std::os::unix::fs::symlink("DefaultEngine.ini", project.path().join("Config/Alias.ini")).unwrap();
+    let files = common::read_tree(project.path());
+
+    for format in ArchiveFormat::ALL {
+        for policy in SymlinkPolicy::ALL {
+            let output = tempfile::tempdir().unwrap();
+            let archive_path = output.path().join(format!("backup.{}", format.extension()));
+            compress_project(project.path(), &archive_path, &BackupProfile::default(), policy, format, &CompressionSettings::default(), None)
+                .await
+                .unwrap();
+
+            let target = tempfile::tempdir().unwrap();
+            let report = extract_archive(&archive_path, target.path(), format, &ExtractOptions::default()).await.unwrap();
+            let music = target.path().join("Content/Music");
+            let alias = target.path().join("Config/Alias.ini");
+            match policy {
+                SymlinkPolicy::Store => {
+                    assert_eq!(report.linked, 2, "{}", format);
+                    assert_eq!(std::fs::read_link(&music).unwrap(), Path::new("Audio"), "{}", format);
+                    assert_eq!(std::fs::read_link(&alias).unwrap(), Path::new("DefaultEngine.ini"), "{}", format);
+                    assert_eq!(common::read_tree(target.path()), files, "{}", format);
+                }
+                SymlinkPolicy::Follow => {
+                    assert!(!music.is_symlink() && music.is_dir(), "{}", format);
+                    assert_eq!(std::fs::read(music.join("Theme.wav")).unwrap(), files["Content/Audio/Theme.wav"], "{}", format);
+                    assert_eq!(std::fs::read(&alias).unwrap(), files["Config/DefaultEngine.ini"], "{}", format);
+                }
+                SymlinkPolicy::Skip => {
+                    assert!(std::fs::symlink_metadata(&music).is_err(), "{}", format);
+                    assert!(std::fs::symlink_metadata(&alias).is_err(), "{}", format);
+                    assert_eq!(common::read_tree(target.path()), files, "{}", format);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn entries_are_never_written_through_links_from_the_archive() {
+    let output = tempfile::tempdir().unwrap();
+    let outside = output.path().join("outside");
+    std::fs::create_dir(&outside).unwrap();
+    let target = output.path().join("target");
+
+    // The link comes first, so a naive extractor would write the file through it
+    let zip_path = output.path().join("evil.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    zip.add_symlink("Escape", outside.to_string_lossy(), zip::write::SimpleFileOptions::default()).unwrap();
+    zip.start_file("Escape/evil.txt", zip::write::SimpleFileOptions::default()).unwrap();
+    std::io::Write::write_all(&mut zip, b"evil").unwrap();
+    zip.finish().unwrap();
+
+    let report = extract_archive(&zip_path, &target, ArchiveFormat::Zip, &ExtractOptions::default()).await.unwrap();
+    assert!(common::read_tree(&outside).is_empty());
+    assert_eq!(std::fs::read(target.join("Escape/evil.txt")).unwrap(), b"evil");
+    assert_eq!(report.linked, 0);
+    assert_eq!(report.link_warnings.len(), 1, "{:?}", report.link_warnings);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn links_never_lead_extraction_outside_the_target() {
+    let output = tempfile::tempdir().unwrap();
+    let outside = output.path().join("outside");
+    std::fs::create_dir(&outside).unwrap();
+    let target = output.path().join("target");
+
+    let zip_path = output.path().join("links.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    zip.add_symlink("Plugins/Shared", outside.to_string_lossy(), options).unwrap();
+    zip.add_symlink("Plugins/Up", "../..", options).unwrap();
+    zip.add_symlink("Plugins/Sideways", "Shared/../../..", options).unwrap();
+    zip.add_symlink("Plugins/Audio", "../Content/Audio", options).unwrap();
+    zip.finish().unwrap();
+
+    let report = extract_archive(&zip_path, &target, ArchiveFormat::Zip, &ExtractOptions::default()).await.unwrap();
+    assert_eq!(report.linked, 1);
+    assert_eq!(report.link_warnings.len(), 3, "{:?}", report.link_warnings);
+    assert_eq!(std::fs::read_link(target.join("Plugins/Audio")).unwrap(), Path::new("../Content/Audio"));
+
+    // A link a user made, or an earlier pull restored, is not written through either
+    std::os::unix::fs::symlink(&outside, target.join("Plugins/Shared")).unwrap();
+    let zip_path = output.path().join("files.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    zip.add_directory("Plugins/Shared/Empty/", options).unwrap();
+    zip.start_file("Plugins/Shared/evil.txt", options).unwrap();
+    std::io::Write::write_all(&mut zip, b"evil").unwrap();
+    zip.start_file("Plugins/Fine.txt", options).unwrap();
+    std::io::Write::write_all(&mut zip, b"fine").unwrap();
+    zip.finish().unwrap();
+
+    let report = extract_archive(&zip_path, &target, ArchiveFormat::Zip, &ExtractOptions::default()).await.unwrap();
+    assert!(std::fs::read_dir(&outside).unwrap().next().is_none());
+    assert_eq!(std::fs::read(target.join("Plugins/Fine.txt")).unwrap(), b"fine");
+    assert_eq!((report.extracted, report.kept), (1, 1));
+    assert_eq!(report.link_warnings.len(), 2, "{:?}", report.link_warnings);
+}
@@ -0,0 +1,265 @@
+mod common;
+
+use std::path::Path;
+use std::time::Duration;
+
+use aws_sdk_s3::Client;
+use common::faults::{Fault, Operation};
+use common::{link_project, noise, write_file, S3Server, BUCKET};
+use rsget::functions::pull::PullOptions;
+use rsget::functions::push::PushOptions;
+use rsget::functions::verify::VerifyOptions;
+use rsget::functions::{pull, push, verify};
+use rsget::tools::aws;
+use rsget::tools::chunk_store::Storage;
+use sha2::{Digest, Sha256};
+
+const MB: usize = 1024 * 1024;
+
+async fn started_server() -> S3Server {
+    let server = S3Server::start().await;
+    server.create_bucket(BUCKET);
+    server
+}
+
+fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+async fn upload(server: &S3Server, key: &str, data: &[u8]) -> anyhow::Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("upload.bin");
+    std::fs::write(&path, data).unwrap();
+    aws::upload_to_yandex_s3(&server.client(), &path, BUCKET, key).await
+}
+
+async fn download(client: &Client, key: &str) -> anyhow::Result<(Vec<u8>, String)> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("download.bin");
+    let digest = aws::download_from_yandex_s3(client, &path, BUCKET, key).await?;
+    Ok((std::fs::read(&path).unwrap(), digest))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_part_server_errors_are_retried() {
+    let server = started_server().await;
+    server.inject(Operation::UploadPart, 1..=2, Fault::ServerError(503));
+    let data = noise(12 * MB, 1);
+
+    upload(&server, "retry/parts.bin", &data).await.unwrap();
+
+    assert_eq!(server.object(BUCKET, "retry/parts.bin").unwrap(), data);
+    assert_eq!(server.calls(Operation::UploadPart), 4);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dropped_connections_are_retried() {
+    let server = started_server().await;
+    server.inject(Operation::PutObject, 1..=1, Fault::DropConnection);
+    server.inject(Operation::GetObject, 1..=1, Fault::DropConnection);
+    let data = noise(64 * 1024, 2);
+
+    upload(&server, "retry/small.bin", &data).await.unwrap();
+    let (downloaded, digest) = download(&server.client(), "retry/small.bin").await.unwrap();
+
+    assert_eq!(downloaded, data);
+    assert_eq!(digest, sha256(&data));
+    assert_eq!(server.calls(Operation::PutObject), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wrong_part_etags_are_recovered_from_list_parts() {
+    let server = started_server().await;
+    server.inject(Operation::UploadPart, 2..=2, Fault::WrongEtag);
+    let data = noise(20 * MB, 3);
+
+    upload(&server, "etag/parts.bin", &data).await.unwrap();
+
+    assert_eq!(server.object(BUCKET, "etag/parts.bin").unwrap(), data);
+    assert_eq!(server.calls(Operation::ListParts), 1);
+    assert_eq!(server.calls(Operation::CompleteMultipartUpload), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn persistent_part_failures_abort_the_upload() {
+    let server = started_server().await;
+    server.inject(Operation::UploadPart, 1..=usize::MAX, Fault::ServerError(500));
+
+    let result = upload(&server, "broken/parts.bin", &noise(6 * MB, 4)).await;
+
+    assert!(result.is_err());
+    assert_eq!(server.calls(Operation::AbortMultipartUpload), 1);
+    assert_eq!(server.pending_uploads(), 0);
+    assert!(server.object(BUCKET, "broken/parts.bin").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn truncated_downloads_resume_with_ranged_gets() {
+    let server = started_server().await;
+    let data = noise(3 * MB, 5);
+    server.put_object(BUCKET, "resume/archive.7z", &data);
+    server.inject(Operation::GetObject, 1..=2, Fault::TruncateBody(MB));
+
+    let (downloaded, digest) = download(&server.client(), "resume/archive.7z").await.unwrap();
+
+    assert_eq!(downloaded, data);
+    assert_eq!(digest, sha256(&data));
+    assert_eq!(server.calls(Operation::GetObject), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn downloads_give_up_after_max_attempts() {
+    let server = started_server().await;
+    server.put_object(BUCKET, "resume/never.7z", &noise(MB, 6));
+    server.inject(Operation::GetObject, 1..=usize::MAX, Fault::TruncateBody(0));
+
+    let result = download(&server.client(), "resume/never.7z").await;
+
+    assert!(result.is_err());
+    assert_eq!(server.calls(Operation::GetObject), aws::MAX_ATTEMPTS as usize);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn latency_beyond_the_attempt_timeout_is_retried() {
+    let server = started_server().await;
+    let data = noise(1024, 7);
+    server.put_object(BUCKET, "slow/object.bin", &data);
+    server.inject(Operation::HeadObject, 1..=1, Fault::Latency(aws::READ_TIMEOUT + Duration::from_secs(5)));
+
+    let (downloaded, _) = download(&server.client(), "slow/object.bin").await.unwrap();
+
+    assert_eq!(downloaded, data);
+    assert_eq!(server.calls(Operation::HeadObject), 2);
+}
+
+fn build_project(root: &Path, name: &str) {
+    write_file(root, &format!("{}.uproject", name), b"{}");
+    write_file(root, "Content/Maps/Main.umap", &noise(7 * MB, 8));
+    write_file(root, "Config/DefaultGame.ini", b"[/Script/EngineSettings.GeneralProjectSettings]\n");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn push_survives_faults_at_every_stage() {
+    let server = started_server().await;
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path(), "Flaky");
+    let config = link_project(project_dir.path(), "Flaky");
+
+    server.inject(Operation::CreateMultipartUpload, 1..=1, Fault::ServerError(503));
+    server.inject(Operation::UploadPart, 1..=1, Fault::DropConnection);
+    server.inject(Operation::CompleteMultipartUpload, 1..=1, Fault::ServerError(500));
+    server.inject(Operation::PutObject, 1..=1, Fault::ServerError(503));
+
+    let summary = push::create_backup(project_dir.path(), &server.client(), &PushOptions::default()).await.unwrap();
+
+    let archive = server.object(BUCKET, &summary.object_key).unwrap();
+    assert!(summary.object_key.starts_with(&config.backups_prefix()));
+    let meta = server.snapshot(&summary.object_key);
+    assert_eq!(meta.sha256, sha256(&archive));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pull_rejects_corrupted_archives() {
+    let server = started_server().await;
+    let client = server.client();
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path(), "Corrupted");
+    link_project(project_dir.path(), "Corrupted");
+    let summary = push::create_backup(project_dir.path(), &client, &PushOptions::default()).await.unwrap();
+
+    server.corrupt(BUCKET, &summary.object_key);
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "Corrupted");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    let error = pull::restore_backup(plan, &client).await.unwrap_err();
+
+    assert!(error.to_string().contains("Checksum mismatch"), "{:#}", error);
+    assert!(common::read_tree(target_dir.path()).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pull_rejects_a_corrupted_volume() {
+    let server = started_server().await;
+    let client = server.client();
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path(), "CorruptedVolume");
+    link_project(project_dir.path(), "CorruptedVolume");
+    let options = PushOptions { volume_size_mb: Some(2), ..Default::default() };
+    let summary = push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+    let second_volume = format!("{}.002", summary.object_key);
+    server.corrupt(BUCKET, &second_volume);
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "CorruptedVolume");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    let error = pull::restore_backup(plan, &client).await.unwrap_err();
+
+    assert!(error.to_string().contains(&format!("Checksum mismatch for {}", second_volume)), "{:#}", error);
+    assert!(common::read_tree(target_dir.path()).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_reports_corrupted_volumes_and_entries() {
+    let server = started_server().await;
+    let client = server.client();
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path(), "Audited");
+    link_project(project_dir.path(), "Audited");
+    let options = PushOptions { volume_size_mb: Some(2), ..Default::default() };
+    let summary = push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+    let second_volume = format!("{}.002", summary.object_key);
+    server.corrupt(BUCKET, &second_volume);
+    let plan = verify::plan_verify(project_dir.path(), &client, &VerifyOptions::default()).await.unwrap();
+    let report = verify::verify_backup(plan, &client).await.unwrap();
+
+    assert!(!report.is_intact());
+    let keys: Vec<_> = report.mismatches.iter().map(|m| m.key.as_str()).collect();
+    assert_eq!(keys, [second_volume.as_str(), summary.object_key.as_str()]);
+    let corrupt: Vec<_> = report.report.corrupt.iter().map(|c| c.name.as_deref()).collect();
+    assert_eq!(corrupt, [Some("Content/Maps/Main.umap")]);
+    assert!(report.missing.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chunked_pull_resumes_interrupted_pack_reads() {
+    let server = started_server().await;
+    let client = server.client();
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path(), "FlakyChunks");
+    link_project(project_dir.path(), "FlakyChunks");
+    let options = PushOptions { storage: Some(Storage::Chunks), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "FlakyChunks");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    let gets = server.calls(Operation::GetObject);
+    server.inject(Operation::GetObject, gets + 1..=gets + 2, Fault::TruncateBody(MB));
+    server.inject(Operation::GetObject, gets + 3..=gets + 3, Fault::DropConnection);
+    pull::restore_backup(plan, &client).await.unwrap();
+
+    assert_eq!(common::read_tree(target_dir.path()), common::read_tree(project_dir.path()));
+    assert!(server.calls(Operation::GetObject) > gets + 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chunked_pull_gives_up_after_max_attempts() {
+    let server = started_server().await;
+    let client = server.client();
+    let project_dir = tempfile::tempdir().unwrap();
+    build_project(project_dir.path(), "BrokenChunks");
+    link_project(project_dir.path(), "BrokenChunks");
+    let options = PushOptions { storage: Some(Storage::Chunks), ..Default::default() };
+    push::create_backup(project_dir.path(), &client, &options).await.unwrap();
+
+    let target_dir = tempfile::tempdir().unwrap();
+    link_project(target_dir.path(), "BrokenChunks");
+    let plan = pull::plan_restore(target_dir.path(), &client, &PullOptions::default()).await.unwrap();
+    let gets = server.calls(Operation::GetObject);
+    server.inject(Operation::GetObject, gets + 1..=usize::MAX, Fault::TruncateBody(0));
+    let error = pull::restore_backup(plan, &client).await.unwrap_err();
+
+    assert!(format!("{:#}", error).contains(&format!("failed after {} attempts", aws::MAX_ATTEMPTS)), "{:#}", error);
+    assert_eq!(server.calls(Operation::GetObject) - gets, aws::MAX_ATTEMPTS as usize);
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use common::write_file;
+use rsget::tools::compressing::{collect_project, collect_project_files, SymlinkPolicy};
+use rsget::utils::project_config::{BackupProfile, ProjectConfig};
+use std::path::Path;
+
+fn collected(root: &Path) -> Vec<String> {
+    collected_with(root, &BackupProfile::default())
+}
+
+fn collected_with(root: &Path, profile: &BackupProfile) -> Vec<String> {
+    let mut paths: Vec<_> = collect_project_files(root, profile, SymlinkPolicy::Store).unwrap().into_iter().map(|f| f.relative_path).collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn defaults_match_whole_path_components() {
+    let project = tempfile::tempdir().unwrap();
+    write_file(project.path(), "Content/BinariesPack/Rock.uasset", b"asset");
+    write_file(project.path(), "Content/Intermediate.uasset", b"asset");
+    write_file(project.path(), "Binaries/Win64/Game.exe", b"exe");
+    write_file(project.path(), "Plugins/Tool/Intermediate/Build/obj.o", b"obj");
+    write_file(project.path(), "Plugins/Tool/Binaries/Win64/Tool.dll", b"dll");
+    write_file(project.path(), "Saved/Autosave.bak", b"bak");
+    write_file(project.path(), ".rsget/config.json", b"{}");
+
+    assert_eq!(
+        collected(project.path()),
+        ["Content/BinariesPack/Rock.uasset", "Content/Intermediate.uasset"]
+    );
+}
+
+#[test]
+fn rsgetignore_adds_negates_and_anchors_patterns() {
+    let project = tempfile::tempdir().unwrap();
+    write_file(
+        project.path(),
+        ".rsgetignore",
+        b"# artists never need these\n/Saved/\n*.wav\n!Content/Audio/Theme.wav\n/Build\n!Binaries/\n",
+    );
+    write_file(project.path(), "Saved/Logs/Game.log", b"log");
+    write_file(project.path(), "Content/Saved/Keep.uasset", b"asset");
+    write_file(project.path(), "Content/Audio/Theme.wav", b"wav");
+    write_file(project.path(), "Content/Audio/Step.wav", b"wav");
+    write_file(project.path(), "Build/Windows/Icon.ico", b"ico");
+    write_file(project.path(), "Source/Build/Rules.cs", b"cs");
+    write_file(project.path(), "Binaries/Win64/Game.exe", b"exe");
+
+    assert_eq!(
+        collected(project.path()),
+        [
+            ".rsgetignore",
+            "Binaries/Win64/Game.exe",
+            "Content/Audio/Theme.wav",
+            "Content/Saved/Keep.uasset",
+            "Source/Build/Rules.cs",
+        ]
+    );
+}
+
+#[test]
+fn invalid_patterns_are_reported_with_their_line() {
+    let project = tempfile::tempdir().unwrap();
+    write_file(project.path(), ".rsgetignore", b"*.tmp\nContent/{Maps,Levels\n");
+
+    let error = collect_project_files(project.path(), &BackupProfile::default(), SymlinkPolicy::Store).err().unwrap();
+
+    assert!(format!("{:#}", error).contains(".rsgetignore:2"), "{:#}", error);
+}
+
+fn build_unreal_project(root: &Path) {
+    write_file(root, "Demo.uproject", b"{}");
+    write_file(root, "Content/Maps/Main.umap", b"map");
+    write_file(root, "Config/DefaultEngine.ini", b"ini");
+    write_file(root, "Source/Demo/Demo.cpp", b"cpp");
+    write_file(root, "Plugins/Tool/Tool.uplugin", b"{}");
+    write_file(root, "Plugins/Tool/Source/Tool.cpp", b"cpp");
+    write_file(root, "Plugins/Tool/Content/Icon.uasset", b"asset");
+    write_file(root, "Binaries/Win64/Demo.exe", b"exe");
+    write_file(root, "Saved/Config/Game.ini", b"ini");
+}
+
+#[test]
+fn default_profiles_select_their_subsets() {
+    let project = tempfile::tempdir().unwrap();
+    build_unreal_project(project.path());
+    let config = ProjectConfig::new("Demo", "bucket");
+
+    assert_eq!(
+        collected_with(project.path(), config.profile("content").unwrap()),
+        ["Content/Maps/Main.umap", "Demo.uproject"]
+    );
+    assert_eq!(
+        collected_with(project.path(), config.profile("source").unwrap()),
+        [
+            "Config/DefaultEngine.ini",
+            "Demo.uproject",
+            "Plugins/Tool/Source/Tool.cpp",
+            "Plugins/Tool/Tool.uplugin",
+            "Source/Demo/Demo.cpp",
+        ]
+    );
+    assert!(collected_with(project.path(), config.profile("full").unwrap()).contains(&"Binaries/Win64/Demo.exe".to_string()));
+}
+
+#[test]
+fn profile_excludes_apply_after_rsgetignore() {
+    let project = tempfile::tempdir().unwrap();
+    build_unreal_project(project.path());
+    write_file(project.path(), ".rsgetignore", b"/Saved/\n");
+    let profile = BackupProfile {
+        include: vec!["/Content/".to_string(), "/Saved/".to_string()],
+        exclude: vec!["!/Saved/".to_string(), "*.umap".to_string()],
+    };
+
+    assert_eq!(collected_with(project.path(), &profile), ["Saved/Config/Game.ini"]);
+}
+
+#[test]
+fn unknown_profiles_list_the_available_ones() {
+    let error = ProjectConfig::new("Demo", "bucket").profile("art").unwrap_err();
+
+    assert_eq!(error.to_string(), "Unknown profile 'art', available: content, full, source");
+}
+
+#[test]
+fn empty_directories_are_collected_unless_ignored() {
+    let project = tempfile::tempdir().unwrap();
+    write_file(project.path(), "Content/Maps/Main.umap", b"map");
+    write_file(project.path(), "Saved/Logs/Editor.bak", b"log");
+    for dir in ["Content/Maps/Empty", "Content/Audio/Music/Ambient", "Content/Audio/Sfx", "Intermediate/Empty", "Source/Empty"] {
+        std::fs::create_dir_all(project.path().join(dir)).unwrap();
+    }
+
+    let contents = collect_project(project.path(), &BackupProfile::default(), SymlinkPolicy::Store).unwrap();
+    let mut dirs: Vec<_> = contents.empty_dirs.into_iter().map(|d| d.relative_path).collect();
+    dirs.sort();
+    assert_eq!(dirs, ["Content/Audio/Music/Ambient", "Content/Audio/Sfx", "Content/Maps/Empty", "Source/Empty"]);
+
+    let content = BackupProfile { include: vec!["/Content/".to_string()], ..Default::default() };
+    let contents = collect_project(project.path(), &content, SymlinkPolicy::Store).unwrap();
+    assert_eq!(contents.empty_dirs.len(), 3);
+}
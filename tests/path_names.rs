@@ -0,0 +1,82 @@
+mod common;
+
+use common::write_file;
+use rsget::tools::compressing::{collect_project, SymlinkPolicy};
+use rsget::tools::path_names::{PathProblem, PortableNames, UnportablePaths};
+use rsget::utils::project_config::BackupProfile;
+use std::path::Path;
+
+fn problems(root: &Path) -> Vec<PathProblem> {
+    let error = collect_project(root, &BackupProfile::default(), SymlinkPolicy::Store).err().unwrap();
+    error.downcast::<UnportablePaths>().unwrap().problems
+}
+
+#[test]
+fn names_are_slash_separated_and_composed() {
+    let mut names = PortableNames::default();
+    let decomposed = Path::new("Content").join("Cafe\u{301}").join("Sign.uasset");
+    assert_eq!(names.add(&decomposed), "Content/Caf\u{e9}/Sign.uasset");
+    assert_eq!(names.add(Path::new("Config/DefaultEngine.ini")), "Config/DefaultEngine.ini");
+    assert!(names.check().is_ok());
+}
+
+#[test]
+fn names_differing_by_case_or_normalization_conflict() {
+    let project = tempfile::tempdir().unwrap();
+    write_file(project.path(), "Content/Hero.uasset", b"a");
+    write_file(project.path(), "Content/hero.uasset", b"b");
+    write_file(project.path(), "Content/Caf\u{e9}.uasset", b"c");
+    write_file(project.path(), "Content/Cafe\u{301}.uasset", b"d");
+    write_file(project.path(), "Content/Maps/Main.umap", b"e");
+
+    assert_eq!(
+        problems(project.path()),
+        [
+            PathProblem::Conflict(vec!["Content/Caf\u{e9}.uasset".to_string(); 2]),
+            PathProblem::Conflict(vec!["Content/Hero.uasset".to_string(), "Content/hero.uasset".to_string()]),
+        ]
+    );
+}
+
+#[test]
+fn directories_differing_by_case_conflict() {
+    let mut names = PortableNames::default();
+    names.add(Path::new("Content/A/Hero.uasset"));
+    names.add(Path::new("content/b/Map.umap"));
+    names.add(Path::new("Content/a/Sword.uasset"));
+    assert_eq!(
+        names.check().unwrap_err().problems,
+        [
+            PathProblem::Conflict(vec!["Content".to_string(), "content".to_string()]),
+            PathProblem::Conflict(vec!["Content/A".to_string(), "Content/a".to_string()]),
+        ]
+    );
+
+    // A directory added as an entry of its own is the same spelling as its files' parent
+    let mut names = PortableNames::default();
+    names.add(Path::new("Content/Maps/Main.umap"));
+    names.add(Path::new("Content/Maps"));
+    names.add(Path::new("Content"));
+    assert!(names.check().is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn names_windows_rejects_are_reported_once() {
+    let project = tempfile::tempdir().unwrap();
+    write_file(project.path(), "Content/What?/A.uasset", b"a");
+    write_file(project.path(), "Content/What?/B.uasset", b"b");
+    write_file(project.path(), "Config/aux.ini", b"c");
+    write_file(project.path(), "Notes/todo. ", b"d");
+    write_file(project.path(), "Notes/back\\slash.txt", b"e");
+    write_file(project.path(), "Content/Fine.uasset", b"f");
+
+    let paths: Vec<_> = problems(project.path())
+        .into_iter()
+        .map(|problem| match problem {
+            PathProblem::Invalid { path, .. } => path,
+            PathProblem::Conflict(names) => panic!("unexpected conflict {:?}", names),
+        })
+        .collect();
+    assert_eq!(paths, ["Config/aux.ini", "Content/What?", "Notes/back\\slash.txt", "Notes/todo. "]);
+}